@@ -2,47 +2,118 @@ use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::thread::JoinHandle;
+use crate::behaviours::Behaviour;
 use crate::raytracer::SceneObject;
 use crate::matrices::Vector;
+use crate::messages::Message;
+use crate::network::Endpoint;
 
 pub trait Agent {
-    fn act(&self, slf: Arc<Mutex<BasicAgent>>) -> JoinHandle<()>;
+    fn act(&self, slf: Arc<Mutex<Self>>) -> JoinHandle<()> where Self: Sized;
     fn get_location(&self) -> Vector;
     fn set_location(&mut self, togo: &Vector);
     fn get_body(&self) -> Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>;
     fn distance_from(&self, point: &Vector) -> f64;
 }
-pub struct BasicAgent<> {
+//what a Behaviour gets to see of the agent it is steering
+#[derive(Debug, Clone)]
+pub struct AgentState {
+    pub id: i64,
+    pub location: Vector,
+    pub velocity: Vector, //the move the agent made on its last tick
+    pub clock: u64,
+}
+pub struct BasicAgent<B: Behaviour> {
     id: i64,
     body: Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>,
-    senders: Arc<Vec<Mutex<Sender<String>>>>, //each BasicAgent will have a sender channel for each other agent
-    receiver: Mutex<Receiver<String>>,
+    senders: Vec<Sender<Message>>, //a sender channel for each agent this one talks to
+    receiver: Receiver<Message>,
+    expected: usize, //number of messages to wait for each tick
+    velocity: Vector,
+    clock: u64,
+    behaviour: B,
 }
-impl BasicAgent<> {
-    pub fn new<>(id: i64, body: Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>, sender: Arc<Vec<Mutex<Sender<String>>>>, receiver: Mutex<Receiver<String>>) -> BasicAgent {
-        return BasicAgent { id, body, senders: sender, receiver }
+impl<B: Behaviour> BasicAgent<B> {
+    pub fn new(id: i64, body: Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>, endpoint: Endpoint, behaviour: B) -> BasicAgent<B> {
+        BasicAgent { id, body, senders: endpoint.senders, receiver: endpoint.receiver, expected: endpoint.expected, velocity: Vector::origin(), clock: 0, behaviour }
+    }
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+    pub fn velocity(&self) -> Vector {
+        self.velocity
+    }
+    pub fn set_velocity(&mut self, velocity: &Vector) {
+        self.velocity = *velocity;
+    }
+    pub fn state(&self) -> AgentState {
+        AgentState { id: self.id, location: self.body.lock().unwrap().get_location(), velocity: self.velocity, clock: self.clock }
+    }
+    //swaps the agents this one talks to, e.g. when the topology changes between ticks
+    pub fn rewire(&mut self, senders: Vec<Sender<Message>>, expected: usize) {
+        self.senders = senders;
+        self.expected = expected;
+    }
+    //sends this agent's current state to every agent it is connected to
+    pub fn publish(&mut self) {
+        self.clock += 1;
+        let to_send = Message::state(self.id, self.clock, self.body.lock().unwrap().get_location(), self.velocity);
+        for sender in self.senders.iter() {
+            //a receiver that has hung up should not take this agent down with it
+            let _ = sender.send(to_send.clone());
+        }
+    }
+    //handles everything already waiting in the receiver, in sender order so the outcome does not depend on arrival order
+    pub fn consume(&mut self) {
+        let mut received = vec![];
+        while let Ok(message) = self.receiver.try_recv() {
+            received.push(message);
+        }
+        received.sort_by_key(|message| (message.sender, message.timestamp));
+        let mut inbox = vec![];
+        for message in received {
+            self.receive(message, &mut inbox);
+        }
+        self.update(&inbox);
+    }
+    fn receive(&mut self, message: Message, inbox: &mut Vec<Message>) {
+        self.clock = self.clock.max(message.timestamp) + 1;
+        if message.sender != self.id {
+            inbox.push(message);
+        }
+    }
+    fn update(&mut self, inbox: &[Message]) {
+        let state = self.state();
+        let to_move = self.behaviour.decide(&state, inbox);
+        self.body.lock().unwrap().set_location(&state.location.return_plus(&to_move));
+        self.velocity = to_move;
+    }
+    pub fn behaviour(&self) -> &B {
+        &self.behaviour
+    }
+    pub fn behaviour_mut(&mut self) -> &mut B {
+        &mut self.behaviour
     }
 }
-impl Agent for BasicAgent<> {
+impl<B: Behaviour + 'static> Agent for BasicAgent<B> {
 
-    //act(_) will ask the other vectors where they are and go towards them
-    fn act(&self, slf: Arc<Mutex<BasicAgent>>) -> JoinHandle<()>{
+    //act(_) will tell the other agents where it is, listen to where they are and let its behaviour decide where to go
+    fn act(&self, slf: Arc<Mutex<BasicAgent<B>>>) -> JoinHandle<()>{
         let h = thread::spawn(move || {
             let mut slf_unlocked = slf.lock().unwrap();
-            let location = slf_unlocked.get_location();
-            let to_send = String::from(format!("{} {} {} {}", slf_unlocked.id, location.x, location.y, location.z));
-            let num_senders = slf_unlocked.senders.len();
-            for i in 0..num_senders{
-                slf_unlocked.senders[i].lock().unwrap().send(to_send.clone()).unwrap();
-            }
-            for _ in 0..num_senders {
-                let received =  slf_unlocked.receiver.lock().unwrap().recv().unwrap();
-                let info: Vec<&str> = received.split(' ').collect();
-                let to_add = Vector::vector_between(&slf_unlocked.get_location(), &Vector::new(info[1].parse::<f64>().unwrap(), info[2].parse::<f64>().unwrap(), info[3].parse::<f64>().unwrap())).return_multiply(0.01);
-                let new_location = &slf_unlocked.get_location().return_plus(&to_add);
-                slf_unlocked.set_location(new_location);
-                //println!("{}", info[0])
+            slf_unlocked.publish();
+            let mut inbox = vec![];
+            for _ in 0..slf_unlocked.expected {
+                let received = match slf_unlocked.receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => break
+                };
+                slf_unlocked.receive(received, &mut inbox);
             }
+            slf_unlocked.update(&inbox);
         });
         return h;
     }
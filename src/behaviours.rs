@@ -0,0 +1,188 @@
+use crate::agents::AgentState;
+use crate::matrices::Vector;
+use crate::messages::Message;
+use crate::random::Rng;
+
+//a Behaviour looks at the agent's own state and what it heard this tick and returns the move it wants to make
+pub trait Behaviour: Send {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector;
+}
+impl Behaviour for Box<dyn Behaviour> {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        (**self).decide(state, inbox)
+    }
+}
+
+//moves a fraction of the way toward every other agent
+pub struct Attraction {
+    pub rate: f64
+}
+impl Default for Attraction {
+    fn default() -> Attraction {
+        Attraction { rate: 0.01 }
+    }
+}
+impl Behaviour for Attraction {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        let mut to_move = Vector::origin();
+        for other_location in inbox.iter().filter_map(|m| m.location()) {
+            to_move.plus(&Vector::vector_between(&state.location, &other_location).return_multiply(self.rate));
+        }
+        to_move
+    }
+}
+
+//pushes away from any agent closer than radius, harder the closer it is
+pub struct Repulsion {
+    pub rate: f64,
+    pub radius: f64
+}
+impl Behaviour for Repulsion {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        let mut to_move = Vector::origin();
+        for other_location in inbox.iter().filter_map(|m| m.location()) {
+            let away = Vector::vector_between(&other_location, &state.location);
+            let distance = away.magnitude();
+            if distance > 0.0 && distance < self.radius {
+                to_move.plus(&away.return_normalised().return_multiply(self.rate * (self.radius - distance)));
+            }
+        }
+        to_move
+    }
+}
+
+//takes a step of fixed length in a random direction every tick
+pub struct Wander {
+    pub step: f64,
+    rng: Rng
+}
+impl Wander {
+    pub fn new(step: f64, seed: u64) -> Wander {
+        Wander { step, rng: Rng::new(seed) }
+    }
+}
+impl Behaviour for Wander {
+    fn decide(&mut self, _state: &AgentState, _inbox: &[Message]) -> Vector {
+        self.rng.unit_vector().return_multiply(self.step)
+    }
+}
+
+//closes in on the leader until it is within distance of it, the leader itself stays put
+pub struct FollowTheLeader {
+    pub leader: i64,
+    pub rate: f64,
+    pub distance: f64
+}
+impl Behaviour for FollowTheLeader {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        if state.id == self.leader {
+            return Vector::origin();
+        }
+        let leader_location = inbox.iter().filter(|m| m.sender == self.leader).find_map(|m| m.location());
+        match leader_location {
+            None => Vector::origin(),
+            Some(leader_location) => {
+                let towards = Vector::vector_between(&state.location, &leader_location);
+                let gap = towards.magnitude() - self.distance;
+                if gap <= 0.0 {
+                    return Vector::origin();
+                }
+                towards.return_normalised().return_multiply(gap * self.rate)
+            }
+        }
+    }
+}
+
+//steers back inside the box spanned by min and max whenever the agent has left it
+pub struct StayInBounds {
+    pub min: Vector,
+    pub max: Vector,
+    pub rate: f64
+}
+impl Behaviour for StayInBounds {
+    fn decide(&mut self, state: &AgentState, _inbox: &[Message]) -> Vector {
+        let push = |value: f64, min: f64, max: f64| {
+            if value < min {
+                (min - value) * self.rate
+            }
+            else if value > max {
+                (max - value) * self.rate
+            }
+            else {
+                0.0
+            }
+        };
+        Vector::new(
+            push(state.location.x, self.min.x, self.max.x),
+            push(state.location.y, self.min.y, self.max.y),
+            push(state.location.z, self.min.z, self.max.z),
+        )
+    }
+}
+
+//Reynolds' boids: steer away from crowding neighbours, match their heading and move toward their centre
+pub struct Boids {
+    pub separation: f64,
+    pub alignment: f64,
+    pub cohesion: f64,
+    pub radius: f64, //only agents closer than this count as neighbours
+    pub max_speed: f64
+}
+impl Default for Boids {
+    fn default() -> Boids {
+        Boids { separation: 1.5, alignment: 0.05, cohesion: 0.01, radius: 300.0, max_speed: 5.0 }
+    }
+}
+impl Behaviour for Boids {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        let mut separation = Vector::origin();
+        let mut average_velocity = Vector::origin();
+        let mut centre = Vector::origin();
+        let mut neighbours = 0;
+        for message in inbox {
+            let other_location = match message.location() {
+                Some(location) => location,
+                None => continue
+            };
+            let away = Vector::vector_between(&other_location, &state.location);
+            let distance = away.magnitude();
+            if distance >= self.radius {
+                continue;
+            }
+            if distance > 0.0 {
+                separation.plus(&away.return_normalised().return_multiply(1.0 - distance / self.radius));
+            }
+            average_velocity.plus(&message.velocity().unwrap_or(Vector::origin()));
+            centre.plus(&other_location);
+            neighbours += 1;
+        }
+        let mut velocity = state.velocity;
+        if neighbours > 0 {
+            let n = neighbours as f64;
+            let alignment = Vector::vector_between(&state.velocity, &average_velocity.return_multiply(1.0 / n));
+            let cohesion = Vector::vector_between(&state.location, &centre.return_multiply(1.0 / n));
+            velocity.plus(&separation.return_multiply(self.separation));
+            velocity.plus(&alignment.return_multiply(self.alignment));
+            velocity.plus(&cohesion.return_multiply(self.cohesion));
+        }
+        let speed = velocity.magnitude();
+        if speed > self.max_speed {
+            velocity = velocity.return_multiply(self.max_speed / speed);
+        }
+        velocity
+    }
+}
+
+//sums the moves of several behaviours, e.g. attraction plus stay-in-bounds
+pub struct Combined {
+    pub behaviours: Vec<Box<dyn Behaviour>>
+}
+impl Behaviour for Combined {
+    fn decide(&mut self, state: &AgentState, inbox: &[Message]) -> Vector {
+        let mut to_move = Vector::origin();
+        for behaviour in self.behaviours.iter_mut() {
+            to_move.plus(&behaviour.decide(state, inbox));
+        }
+        to_move
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+//pixel_data is what draw returns: width * height rgb triples, row by row from the top
+
+//byte length of a width by height rgb image, None if it does not fit in memory
+fn rgb_length(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)?.checked_mul(3)
+}
+//the encoders panic on pixel data that does not match the size, so the writers check it first
+fn check_image(width: u32, height: u32, pixel_data: &[u8]) -> io::Result<()> {
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("a {}x{} image has no pixels", width, height)));
+    }
+    if rgb_length(width, height) != Some(pixel_data.len()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} bytes of pixel data do not make a {}x{} rgb image", pixel_data.len(), width, height)));
+    }
+    Ok(())
+}
+
+pub fn encode_ppm(width: u32, height: u32, pixel_data: &[u8]) -> Vec<u8> {
+    let mut to_return = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    to_return.extend_from_slice(pixel_data);
+    to_return
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+fn png_chunk(to_return: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    to_return.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = to_return.len();
+    to_return.extend_from_slice(kind);
+    to_return.extend_from_slice(data);
+    let crc = crc32(&to_return[start..]);
+    to_return.extend_from_slice(&crc.to_be_bytes());
+}
+//zlib stream made of stored (uncompressed) deflate blocks, which every png reader accepts
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut to_return = vec![0x78, 0x01];
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        to_return.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        to_return.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let length = block.len() as u16;
+        to_return.extend_from_slice(&length.to_le_bytes());
+        to_return.extend_from_slice(&(!length).to_le_bytes());
+        to_return.extend_from_slice(block);
+    }
+    to_return.extend_from_slice(&adler32(data).to_be_bytes());
+    to_return
+}
+pub fn encode_png(width: u32, height: u32, pixel_data: &[u8]) -> Vec<u8> {
+    assert!(width > 0 && height > 0, "pngs must be at least 1x1, not {}x{}", width, height);
+    assert_eq!(Some(pixel_data.len()), rgb_length(width, height), "pixel data does not match a {}x{} rgb image", width, height);
+    let mut to_return = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); //8 bit rgb, no interlacing
+    png_chunk(&mut to_return, b"IHDR", &header);
+    //every scanline starts with its filter type, 0 meaning unfiltered
+    let mut scanlines = Vec::with_capacity(pixel_data.len() + height as usize);
+    for row in pixel_data.chunks(3 * width as usize) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+    png_chunk(&mut to_return, b"IDAT", &zlib_stored(&scanlines));
+    png_chunk(&mut to_return, b"IEND", &[]);
+    to_return
+}
+
+//gifs only hold 256 colours, so frames are mapped onto a 6x6x6 colour cube followed by a 40 step grey ramp
+fn gif_palette() -> Vec<u8> {
+    let mut palette = vec![];
+    for r in 0..6u32 {
+        for g in 0..6u32 {
+            for b in 0..6u32 {
+                palette.extend_from_slice(&[(r * 51) as u8, (g * 51) as u8, (b * 51) as u8]);
+            }
+        }
+    }
+    for grey in 0..40u32 {
+        let level = (grey * 255 / 39) as u8;
+        palette.extend_from_slice(&[level, level, level]);
+    }
+    palette
+}
+fn gif_palette_index(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let (cr, cg, cb) = (level(r), level(g), level(b));
+    let cube_error = [(r, cr), (g, cg), (b, cb)].iter().map(|&(c, l)| (c as i32 - (l * 51) as i32).pow(2)).sum::<i32>();
+    let grey = (r as u32 + g as u32 + b as u32 + 1) / 3;
+    let grey_step = (grey * 39 + 127) / 255;
+    let grey_level = (grey_step * 255 / 39) as i32;
+    let grey_error = [r, g, b].iter().map(|&c| (c as i32 - grey_level).pow(2)).sum::<i32>();
+    if grey_error < cube_error {
+        (216 + grey_step) as u8
+    }
+    else {
+        (cr * 36 + cg * 6 + cb) as u8
+    }
+}
+//variable width lzw as gif wants it: 8 bit symbols, codes from 9 up to 12 bits, packed least significant bit first
+fn gif_lzw(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    let mut packed = vec![];
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    let mut emit = |code: u32, size: u32, packed: &mut Vec<u8>| {
+        buffer |= code << bits;
+        bits += size;
+        while bits >= 8 {
+            packed.push((buffer & 0xFF) as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    };
+    let mut dictionary: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut size = 9;
+    let mut next_code = 258;
+    emit(CLEAR, size, &mut packed);
+    let mut prefix: Option<u32> = None;
+    for &index in indices {
+        prefix = match prefix {
+            None => Some(index as u32),
+            Some(code) => match dictionary.get(&(code, index)) {
+                Some(&longer) => Some(longer),
+                None => {
+                    emit(code, size, &mut packed);
+                    if next_code < 4096 {
+                        if next_code == 1 << size {
+                            size += 1;
+                        }
+                        dictionary.insert((code, index), next_code);
+                        next_code += 1;
+                    }
+                    else {
+                        emit(CLEAR, size, &mut packed);
+                        dictionary.clear();
+                        size = 9;
+                        next_code = 258;
+                    }
+                    Some(index as u32)
+                }
+            }
+        };
+    }
+    if let Some(code) = prefix {
+        emit(code, size, &mut packed);
+        if next_code < 4096 && next_code == 1 << size {
+            size += 1;
+        }
+    }
+    emit(END, size, &mut packed);
+    emit(0, 7, &mut packed); //flush the last partial byte
+    packed
+}
+//looping animated gif, delay is rounded to the hundredths of a second gifs count in
+pub fn encode_gif(width: u32, height: u32, frames: &[Vec<u8>], delay: Duration) -> Vec<u8> {
+    assert!(width <= u16::MAX as u32 && height <= u16::MAX as u32, "gifs are at most {} pixels across, not {}x{}", u16::MAX, width, height);
+    let mut to_return = b"GIF89a".to_vec();
+    to_return.extend_from_slice(&(width as u16).to_le_bytes());
+    to_return.extend_from_slice(&(height as u16).to_le_bytes());
+    to_return.extend_from_slice(&[0xF7, 0, 0]); //global 256 colour table
+    to_return.extend_from_slice(&gif_palette());
+    to_return.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    to_return.extend_from_slice(b"NETSCAPE2.0");
+    to_return.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]); //loop forever
+    let centiseconds = ((delay.as_millis() + 5) / 10).min(u16::MAX as u128) as u16;
+    for frame in frames {
+        assert_eq!(Some(frame.len()), rgb_length(width, height), "frame does not match a {}x{} rgb image", width, height);
+        to_return.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        to_return.extend_from_slice(&centiseconds.to_le_bytes());
+        to_return.extend_from_slice(&[0x00, 0x00]);
+        to_return.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+        to_return.extend_from_slice(&(width as u16).to_le_bytes());
+        to_return.extend_from_slice(&(height as u16).to_le_bytes());
+        to_return.push(0x00);
+        let indices: Vec<u8> = frame.chunks(3).map(|p| gif_palette_index(p[0], p[1], p[2])).collect();
+        to_return.push(8);
+        for block in gif_lzw(&indices).chunks(255) {
+            to_return.push(block.len() as u8);
+            to_return.extend_from_slice(block);
+        }
+        to_return.push(0x00);
+    }
+    to_return.push(0x3B);
+    to_return
+}
+
+pub fn write_ppm(path: impl AsRef<Path>, width: u32, height: u32, pixel_data: &[u8]) -> io::Result<()> {
+    check_image(width, height, pixel_data)?;
+    File::create(path)?.write_all(&encode_ppm(width, height, pixel_data))
+}
+pub fn write_png(path: impl AsRef<Path>, width: u32, height: u32, pixel_data: &[u8]) -> io::Result<()> {
+    check_image(width, height, pixel_data)?;
+    File::create(path)?.write_all(&encode_png(width, height, pixel_data))
+}
+//picks the format from the file extension
+pub fn write_image(path: impl AsRef<Path>, width: u32, height: u32, pixel_data: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref() {
+        Some("ppm") => write_ppm(path, width, height, pixel_data),
+        Some("png") => write_png(path, width, height, pixel_data),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot tell the image format of {}", path.display()))),
+    }
+}
+pub fn write_gif(path: impl AsRef<Path>, width: u32, height: u32, frames: &[Vec<u8>], delay: Duration) -> io::Result<()> {
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("a {}x{} image is too large for a gif", width, height)));
+    }
+    for frame in frames {
+        check_image(width, height, frame)?;
+    }
+    File::create(path)?.write_all(&encode_gif(width, height, frames, delay))
+}
+//writes frames as directory/prefix_00000.png, directory/prefix_00001.png, ... and returns the paths
+pub fn write_png_sequence(directory: impl AsRef<Path>, prefix: &str, width: u32, height: u32, frames: &[Vec<u8>]) -> io::Result<Vec<PathBuf>> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    let mut paths = vec![];
+    for (number, frame) in frames.iter().enumerate() {
+        let path = directory.join(format!("{}_{:05}.png", prefix, number));
+        write_png(&path, width, height, frame)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //undoes zlib_stored, checking every block header and the adler32 trailer on the way
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        let mut data = vec![];
+        let mut at = 2;
+        loop {
+            let last = stream[at] & 1 == 1;
+            assert_eq!(stream[at] >> 1, 0, "only stored blocks are written");
+            let length = u16::from_le_bytes([stream[at + 1], stream[at + 2]]);
+            let complement = u16::from_le_bytes([stream[at + 3], stream[at + 4]]);
+            assert_eq!(length, !complement);
+            data.extend_from_slice(&stream[at + 5..at + 5 + length as usize]);
+            at += 5 + length as usize;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(&stream[at..], &adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn checksums_match_their_reference_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn png_decodes_back_to_the_pixels() {
+        //72000 bytes of pixels, so the image data needs more than one stored block
+        let (width, height) = (200, 120);
+        let pixels: Vec<u8> = (0..3 * width * height).map(|i| (i * 7 % 251) as u8).collect();
+        let png = encode_png(width, height, &pixels);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+        let mut at = 8;
+        let mut chunks = vec![];
+        while at < png.len() {
+            let length = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let kind_and_data = &png[at + 4..at + 8 + length];
+            let crc = u32::from_be_bytes(png[at + 8 + length..at + 12 + length].try_into().unwrap());
+            assert_eq!(crc, crc32(kind_and_data));
+            chunks.push((kind_and_data[..4].to_vec(), kind_and_data[4..].to_vec()));
+            at += 12 + length;
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, vec![&b"IHDR"[..], b"IDAT", b"IEND"]);
+        assert_eq!(&chunks[0].1[..8], &[0, 0, 0, 200, 0, 0, 0, 120]);
+        let scanlines = inflate_stored(&chunks[1].1);
+        assert_eq!(scanlines.len(), pixels.len() + height as usize);
+        for (row, scanline) in scanlines.chunks(1 + 3 * width as usize).enumerate() {
+            assert_eq!(scanline[0], 0);
+            assert_eq!(&scanline[1..], &pixels[row * 3 * width as usize..(row + 1) * 3 * width as usize]);
+        }
+    }
+
+    //reads the codes gif_lzw packs, returning the indices and how many times the dictionary was cleared
+    fn lzw_decode(packed: &[u8]) -> (Vec<u8>, usize) {
+        let mut at = 0;
+        let mut read = |size: usize| {
+            let code = (0..size).map(|bit| ((packed[(at + bit) / 8] >> ((at + bit) % 8)) as u32 & 1) << bit).sum::<u32>();
+            at += size;
+            code
+        };
+        let fresh_table = || -> Vec<Vec<u8>> { (0..=255).map(|index| vec![index]).chain([vec![], vec![]]).collect() };
+        let (mut table, mut size, mut previous, mut clears) = (fresh_table(), 9, None::<Vec<u8>>, 0);
+        let mut indices = vec![];
+        loop {
+            let code = read(size) as usize;
+            if code == 256 {
+                (table, size, previous) = (fresh_table(), 9, None);
+                clears += 1;
+                continue;
+            }
+            if code == 257 {
+                break;
+            }
+            let entry = if code < table.len() { table[code].clone() } else {
+                let mut entry = previous.clone().expect("a code not yet in the table must follow another code");
+                entry.push(entry[0]);
+                entry
+            };
+            if let Some(mut longer) = previous.take() {
+                longer.push(entry[0]);
+                if table.len() < 4096 {
+                    table.push(longer);
+                }
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+            indices.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+        (indices, clears)
+    }
+
+    #[test]
+    fn lzw_round_trips_through_dictionary_resets() {
+        //noisy indices add a code for almost every symbol, so the 4096 code table fills up several times
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let indices: Vec<u8> = (0..20000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 56) as u8
+        }).collect();
+        let (decoded, clears) = lzw_decode(&gif_lzw(&indices));
+        assert_eq!(decoded, indices);
+        assert!(clears > 1, "the dictionary was never reset");
+        let flat = vec![7; 5000];
+        assert_eq!(lzw_decode(&gif_lzw(&flat)).0, flat);
+        assert_eq!(lzw_decode(&gif_lzw(&[])).0, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn gif_writer_rejects_mismatched_frames() {
+        let path = std::env::temp_dir().join("summer2023_never_written.gif");
+        let frames = vec![vec![0; 12], vec![0; 11]];
+        assert_eq!(write_gif(&path, 2, 2, &frames, Duration::from_millis(20)).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(write_gif(&path, 70000, 1, &[vec![0; 210000]], Duration::from_millis(20)).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn writers_reject_mismatched_pixel_data() {
+        let path = std::env::temp_dir().join("summer2023_never_written.png");
+        for (width, height, length) in [(0, 0, 0), (0, 4, 0), (2, 2, 11), (u32::MAX, u32::MAX, 3)] {
+            let error = write_png(&path, width, height, &vec![0; length]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            let error = write_image(path.with_extension("ppm"), width, height, &vec![0; length]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(!path.exists());
+    }
+}
@@ -1,6 +1,13 @@
 pub mod raytracer;
 pub mod agents;
+pub mod behaviours;
+pub mod image;
 pub mod matrices;
+pub mod messages;
+pub mod network;
+pub mod obj;
+pub mod random;
+pub mod simulation;
 
 
 
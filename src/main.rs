@@ -1,83 +1,84 @@
+use std::env;
 use std::process::Command;
-use std::sync::mpsc::{Sender, channel};
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use show_image::{ImageView, ImageInfo, create_window};
 use summer2023::matrices::{Vector};
-use summer2023::raytracer::scene::{Camera, Contents, draw, LightSource, Screen};
-use summer2023::raytracer::{Colour, scene_objects::Sphere};
-use summer2023::agents::{Agent, BasicAgent};
+use summer2023::raytracer::scene::{Camera, LightSource, RenderSettings, Screen};
+use summer2023::raytracer::{Colour, Material, Rgb, SceneObject, scene_objects::Sphere};
+use summer2023::behaviours::Attraction;
+use summer2023::image::{write_gif, write_png_sequence};
+use summer2023::simulation::{Scene, Simulation};
 
-#[show_image::main]
-fn main() {
+const TICKS: u64 = 100;
+
+fn create_simulation() -> Simulation<Attraction> {
     // Creating the test spheres
-    let test_sphere = Sphere { radius: 150.0, location: Vector::new(0.0, 0.0, 1200.0) };
-    let test_sphere2 = Sphere { radius: 120.0, location: Vector::new(150.0, 150.0, 1400.0) };
-    let test_sphere3 = Sphere { radius: 100.0, location: Vector::new(-100.0, -20.0, 1000.0) };
+    let test_sphere = Sphere { radius: 150.0, location: Vector::new(0.0, 0.0, 1200.0), material: Material::default() };
+    let test_sphere2 = Sphere { radius: 120.0, location: Vector::new(150.0, 150.0, 1400.0), material: Material { shininess: 16, ..Material::new(Colour::new(255, 80, 80)) } };
+    let test_sphere3 = Sphere { radius: 100.0, location: Vector::new(-100.0, -20.0, 1000.0), material: Material { specular: 0.2, ..Material::new(Colour::new(80, 255, 80)) } };
 
     // Creating the test Camera, Screen and Light Sources
-    let test_cam = Camera {
-        direction: Vector::new(0.0, 0.0, 1.0),
-        location: Vector::new(0.0, 0.0, 0.0)
-    };
     let test_screen = Screen {
-        distance: 500.0,
         height: 100,
         width: 100,
     };
-    let test_light = LightSource {
-        location: Vector::new(-1000.0, 300.0, 10.0),
-        colour: Colour::new(100, 0, 0),
-        intensity: 19,
-    };
-    let other_test_light = LightSource {
-        location: Vector::new(300.0, 0.0,0.0),
-        colour: Colour::new(0, 100, 0),
-        intensity: 0,
-    };
-    let (sa, ra) = channel();
-    let (sb, rb) = channel();
-    let (sc, rc) = channel();
+    let test_cam = Camera::look_at(Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0), Vector::new(0.0, -1.0, 0.0), 45.0, test_screen.aspect());
+    let test_light = LightSource::point(Vector::new(-1000.0, 300.0, 10.0), Colour::new(255, 0, 0), 2.5e6);
+    let other_test_light = LightSource::point(Vector::new(300.0, 0.0, 0.0), Colour::new(0, 255, 0), 1.5e6);
+    let scene = Scene { camera: test_cam, screen: test_screen, lights: vec![test_light, other_test_light], ambient: Rgb::new(0.02, 0.02, 0.02), objects: vec![], settings: RenderSettings::default() };
 
-    let s_channels: Arc<Vec<Mutex<Sender<String>>>> = Arc::new(vec![Mutex::new(sa.clone()),Mutex::new(sb.clone()), Mutex::new(sc.clone())]);
     // Creating the agents
-    let a = Arc::new(Mutex::new(BasicAgent::new(0, Arc::new(Mutex::new(Box::new(test_sphere))), s_channels.clone(), Mutex::new(ra))));
-    let b = Arc::new(Mutex::new(BasicAgent::new(1, Arc::new(Mutex::new(Box::new(test_sphere2))), s_channels.clone(), Mutex::new(rb))));
-    let c = Arc::new(Mutex::new(BasicAgent::new(2, Arc::new(Mutex::new(Box::new(test_sphere3))), s_channels.clone(), Mutex::new(rc))));
+    let bodies: Vec<Box<dyn SceneObject + Send + Sync>> = vec![Box::new(test_sphere), Box::new(test_sphere2), Box::new(test_sphere3)];
+    Simulation::new(scene, bodies.into_iter().map(|body| (body, Attraction::default())).collect())
+}
 
+fn show_in_window(delay: Duration) {
+    let mut simulation = create_simulation();
 
     // Running the simulation
     let window = create_window("image", Default::default()).expect("Should work");
-    let mut to_show = Vec::new();
-    let agents: Vec<Arc<Mutex<BasicAgent>>> = vec![a.clone(), b.clone(), c.clone()];
-
-    for _ in 0..100 {
-        let mut handles = vec![];
-        let num_agents = agents.len();
-        for agent_num in 0..num_agents {
-            handles.push(agents[agent_num].lock().unwrap().act(agents[agent_num].clone()));
-        }
-        for handle in handles {
-            handle.join().unwrap();
-        }
-
-        let mut scene_objects = vec![];
-        for agent_num in 0..num_agents {
-            let current_agent = agents[agent_num].lock().unwrap();
-            scene_objects.push(current_agent.get_body());
-        }
-        let contents = Contents { objects: scene_objects, light: vec![&test_light, &other_test_light] };
-        let pixel_data = draw(&test_cam, &test_screen, &contents);
-        to_show.push(pixel_data);
-    }
+    let to_show = simulation.record(TICKS);
 
     // Playing the simulation in a loop
+    let screen = &simulation.scene().screen;
     loop {
-        for pd in 0..100{
-            let image = ImageView::new(ImageInfo::rgb8((2 * test_screen.width) as u32, (2 * test_screen.height) as u32), &to_show[pd]);
+        for pd in 0..to_show.len() {
+            let image = ImageView::new(ImageInfo::rgb8(screen.pixel_width(), screen.pixel_height()), &to_show[pd]);
             window.set_image("image-001", image).expect("set image");
-            let mut child = Command::new("sleep").arg("0.02").spawn().unwrap();
+            let mut child = Command::new("sleep").arg(delay.as_secs_f64().to_string()).spawn().unwrap();
             let _result = child.wait().unwrap();
         }
     }
 }
 
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).map(|arg| arg.as_str())
+}
+
+// `--headless <directory>` writes every frame to a png in directory instead of opening a window,
+// `--gif <file>` and `--frames <directory>` export the whole run, `--delay <ms>` sets the time between frames
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let delay = Duration::from_millis(flag_value(&args, "--delay").map(|ms| ms.parse().expect("--delay takes milliseconds")).unwrap_or(20));
+    if let Some(directory) = flag_value(&args, "--headless") {
+        let mut simulation = create_simulation();
+        simulation.run_to_files(TICKS, directory).expect("could not write the frames");
+        return;
+    }
+    let gif = flag_value(&args, "--gif");
+    let frames_directory = flag_value(&args, "--frames");
+    if gif.is_some() || frames_directory.is_some() {
+        let mut simulation = create_simulation();
+        let frames = simulation.record(TICKS);
+        let screen = &simulation.scene().screen;
+        if let Some(path) = gif {
+            write_gif(path, screen.pixel_width(), screen.pixel_height(), &frames, delay).expect("could not write the gif");
+        }
+        if let Some(directory) = frames_directory {
+            write_png_sequence(directory, "frame", screen.pixel_width(), screen.pixel_height(), &frames).expect("could not write the frames");
+        }
+        return;
+    }
+    show_image::run_context(move || show_in_window(delay));
+}
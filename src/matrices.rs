@@ -51,13 +51,16 @@ impl Vector {
     pub fn dot(a: &Vector, b: &Vector) -> f64 {
         a.x * b.x + a.y * b.y + a.z * b.z
     }
+    pub fn cross(a: &Vector, b: &Vector) -> Vector {
+        Vector::new(a.y * b.z - a.z * b.y, a.z * b.x - a.x * b.z, a.x * b.y - a.y * b.x)
+    }
     pub fn copy(&self) -> Vector {
         Vector::new(self.x, self.y, self.z)
     }
     pub fn return_reflected(ray: &Vector, normal: &Vector) -> Vector {
         let mut to_ret = ray.return_normalised();
-        let to_sub = normal.return_normalised();
-        to_sub.return_multiply(2.0 * Vector::dot(&to_ret, &to_sub));
+        let normal = normal.return_normalised();
+        let to_sub = normal.return_multiply(2.0 * Vector::dot(&to_ret, &normal));
         to_ret.minus(&to_sub);
         to_ret
     }
@@ -78,27 +81,29 @@ impl Vector {
     pub fn return_three_matrix_mut(&self, mat: &ThreeMatrix) -> Vector {
         Vector::new(Vector::dot(&mat.row_zero, &self), Vector::dot(&mat.row_one, &self), Vector::dot(&mat.row_two, &self))
     }
-    pub fn three_rotation_matrix_between(_from: &Vector, to: &Vector) -> ThreeMatrix {
-        //TODO generalise the first vector - for now it is only neg z
+    //rotation taking the direction of from onto the direction of to, about the axis perpendicular to both
+    pub fn three_rotation_matrix_between(from: &Vector, to: &Vector) -> ThreeMatrix {
+        let from = from.return_normalised();
         let to = to.return_normalised();
-        let sin_theta = to.y;
-        let cos_theta = (1.0 - sin_theta.powi(2)).powf(0.5);
-        let cos_phi = to.z / cos_theta;
-        let sin_phi  = (1.0 - cos_phi.powi(2)).powf(0.5);
-        let x_rot = ThreeMatrix {
-            row_zero: Vector::new(1.0, 0.0, 0.0),
-            row_one: Vector::new(0.0, cos_theta, -sin_theta),
-            row_two: Vector::new(0.0, sin_theta, cos_theta)
-        };
-        let y_rot = ThreeMatrix {
-            row_zero: Vector::new(-cos_phi, 0.0, -sin_phi),
-            row_one: Vector::new(0.0, 1.0, 0.0),
-            row_two: Vector::new(sin_phi, 0.0, -cos_phi)
-        };
-        let a = ThreeMatrix::return_multiply(&x_rot, &y_rot);
-        //let b = a.return_transpose();
-        //let c= ThreeMatrix::return_multiply(&a, &b);
-        a
+        let cos_theta = Vector::dot(&from, &to);
+        if cos_theta < -1.0 + 1e-9 {
+            //opposite directions: any axis perpendicular to from gives a half turn
+            let helper = if from.x.abs() < 0.9 { Vector::new(1.0, 0.0, 0.0) } else { Vector::new(0.0, 1.0, 0.0) };
+            let axis = Vector::cross(&from, &helper).return_normalised();
+            return ThreeMatrix {
+                row_zero: Vector::new(2.0 * axis.x * axis.x - 1.0, 2.0 * axis.x * axis.y, 2.0 * axis.x * axis.z),
+                row_one: Vector::new(2.0 * axis.y * axis.x, 2.0 * axis.y * axis.y - 1.0, 2.0 * axis.y * axis.z),
+                row_two: Vector::new(2.0 * axis.z * axis.x, 2.0 * axis.z * axis.y, 2.0 * axis.z * axis.z - 1.0)
+            };
+        }
+        //Rodrigues' formula, I + [v]x + [v]x^2 / (1 + cos), with v = from x to
+        let v = Vector::cross(&from, &to);
+        let k = 1.0 / (1.0 + cos_theta);
+        ThreeMatrix {
+            row_zero: Vector::new(1.0 - k * (v.y * v.y + v.z * v.z), k * v.x * v.y - v.z, k * v.x * v.z + v.y),
+            row_one: Vector::new(k * v.x * v.y + v.z, 1.0 - k * (v.x * v.x + v.z * v.z), k * v.y * v.z - v.x),
+            row_two: Vector::new(k * v.x * v.z - v.y, k * v.y * v.z + v.x, 1.0 - k * (v.x * v.x + v.y * v.y))
+        }
     }
 
     pub fn to_string(&self) -> String {
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::matrices::Vector;
+
+#[derive(Debug, Clone)]
+pub enum Payload {
+    Position(Vector),
+    State { location: Vector, velocity: Vector },
+    Request(String),
+    Reply(String),
+    Custom(String),
+}
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub sender: i64,
+    pub timestamp: u64, //logical (Lamport) clock of the sender when the message was sent
+    pub payload: Payload,
+}
+impl Message {
+    pub fn new(sender: i64, timestamp: u64, payload: Payload) -> Message {
+        Message { sender, timestamp, payload }
+    }
+    pub fn position(sender: i64, timestamp: u64, location: Vector) -> Message {
+        Message::new(sender, timestamp, Payload::Position(location))
+    }
+    pub fn state(sender: i64, timestamp: u64, location: Vector, velocity: Vector) -> Message {
+        Message::new(sender, timestamp, Payload::State { location, velocity })
+    }
+    pub fn location(&self) -> Option<Vector> {
+        match &self.payload {
+            Payload::Position(location) => Some(*location),
+            Payload::State { location, .. } => Some(*location),
+            _ => None
+        }
+    }
+    pub fn velocity(&self) -> Option<Vector> {
+        match &self.payload {
+            Payload::State { velocity, .. } => Some(*velocity),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageError {
+    Empty,
+    MissingField(&'static str),
+    InvalidNumber(String),
+    UnknownKind(String),
+    UnexpectedField(String),
+}
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Empty => write!(f, "empty message"),
+            MessageError::MissingField(field) => write!(f, "message is missing the {} field", field),
+            MessageError::InvalidNumber(value) => write!(f, "could not parse '{}' as a number", value),
+            MessageError::UnknownKind(kind) => write!(f, "unknown message kind '{}'", kind),
+            MessageError::UnexpectedField(field) => write!(f, "unexpected field '{}' after the end of the message", field),
+        }
+    }
+}
+impl std::error::Error for MessageError {}
+
+//wire format: "<sender> <timestamp> <kind> <body>", e.g. "0 12 position 1.5 -2 300"
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.sender, self.timestamp)?;
+        match &self.payload {
+            Payload::Position(location) => write!(f, "position {}", location.to_string()),
+            Payload::State { location, velocity } => write!(f, "state {} {}", location.to_string(), velocity.to_string()),
+            Payload::Request(body) => write!(f, "request {}", body),
+            Payload::Reply(body) => write!(f, "reply {}", body),
+            Payload::Custom(body) => write!(f, "custom {}", body),
+        }
+    }
+}
+//the first whitespace separated field of s and everything after it
+fn next_field(s: &str) -> (Option<&str>, &str) {
+    let s = s.trim_start();
+    if s.is_empty() {
+        return (None, s);
+    }
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (Some(&s[..end]), &s[end..])
+}
+fn parse_number<T: FromStr>(field: Option<&str>, name: &'static str) -> Result<T, MessageError> {
+    let field = field.ok_or(MessageError::MissingField(name))?;
+    field.parse::<T>().map_err(|_| MessageError::InvalidNumber(field.to_string()))
+}
+fn parse_vector<'a>(fields: &mut impl Iterator<Item = &'a str>, names: [&'static str; 3]) -> Result<Vector, MessageError> {
+    let x = parse_number::<f64>(fields.next(), names[0])?;
+    let y = parse_number::<f64>(fields.next(), names[1])?;
+    let z = parse_number::<f64>(fields.next(), names[2])?;
+    Ok(Vector::new(x, y, z))
+}
+fn expect_end<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<(), MessageError> {
+    match fields.next() {
+        Some(field) => Err(MessageError::UnexpectedField(field.to_string())),
+        None => Ok(())
+    }
+}
+impl FromStr for Message {
+    type Err = MessageError;
+
+    fn from_str(s: &str) -> Result<Message, MessageError> {
+        if s.trim().is_empty() {
+            return Err(MessageError::Empty);
+        }
+        let (sender, rest) = next_field(s);
+        let sender = parse_number::<i64>(sender, "sender")?;
+        let (timestamp, rest) = next_field(rest);
+        let timestamp = parse_number::<u64>(timestamp, "timestamp")?;
+        let (kind, rest) = next_field(rest);
+        let kind = kind.ok_or(MessageError::MissingField("kind"))?;
+        //text bodies are kept exactly as written after the single separator following the kind
+        let mut body = rest.chars();
+        body.next();
+        let body = body.as_str();
+        let payload = match kind {
+            "position" => {
+                let mut coords = body.split_whitespace();
+                let location = parse_vector(&mut coords, ["x", "y", "z"])?;
+                expect_end(&mut coords)?;
+                Payload::Position(location)
+            }
+            "state" => {
+                let mut coords = body.split_whitespace();
+                let location = parse_vector(&mut coords, ["x", "y", "z"])?;
+                let velocity = parse_vector(&mut coords, ["vx", "vy", "vz"])?;
+                expect_end(&mut coords)?;
+                Payload::State { location, velocity }
+            }
+            "request" => Payload::Request(body.to_string()),
+            "reply" => Payload::Reply(body.to_string()),
+            "custom" => Payload::Custom(body.to_string()),
+            other => return Err(MessageError::UnknownKind(other.to_string())),
+        };
+        Ok(Message::new(sender, timestamp, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Message holds Vectors, which have no PartialEq, so messages are compared through their Debug output
+    fn round_trip(message: Message) {
+        let parsed: Message = message.to_string().parse().unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", message));
+    }
+
+    #[test]
+    fn every_payload_round_trips() {
+        round_trip(Message::position(0, 12, Vector::new(1.5, -2.0, 300.0)));
+        round_trip(Message::state(3, 4, Vector::new(1.0, 2.0, 3.0), Vector::new(-0.25, 0.0, 1e-3)));
+        round_trip(Message::new(-1, 0, Payload::Request("where are you".to_string())));
+        round_trip(Message::new(2, 7, Payload::Reply("over here".to_string())));
+        round_trip(Message::new(5, 9, Payload::Custom("".to_string())));
+    }
+
+    #[test]
+    fn header_fields_can_be_separated_by_any_whitespace() {
+        let message: Message = "1\t2  position 1 2\t3".parse().unwrap();
+        assert_eq!((message.sender, message.timestamp), (1, 2));
+        assert_eq!(format!("{:?}", message.location()), format!("{:?}", Some(Vector::new(1.0, 2.0, 3.0))));
+    }
+
+    #[test]
+    fn text_bodies_keep_leading_spaces() {
+        let message: Message = "1 2 request   hello".parse().unwrap();
+        match message.payload {
+            Payload::Request(body) => assert_eq!(body, "  hello"),
+            other => panic!("expected a request, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn malformed_messages_are_errors() {
+        assert_eq!("".parse::<Message>().unwrap_err(), MessageError::Empty);
+        assert_eq!("  \t".parse::<Message>().unwrap_err(), MessageError::Empty);
+        assert_eq!("0 1".parse::<Message>().unwrap_err(), MessageError::MissingField("kind"));
+        assert_eq!("0 1 position 1 2".parse::<Message>().unwrap_err(), MessageError::MissingField("z"));
+        assert_eq!("zero 1 position 1 2 3".parse::<Message>().unwrap_err(), MessageError::InvalidNumber("zero".to_string()));
+        assert_eq!("0 1 state 1 2 3 4 five 6".parse::<Message>().unwrap_err(), MessageError::InvalidNumber("five".to_string()));
+        assert_eq!("0 1 shout hello".parse::<Message>().unwrap_err(), MessageError::UnknownKind("shout".to_string()));
+        assert_eq!("0 1 position 1 2 3 4".parse::<Message>().unwrap_err(), MessageError::UnexpectedField("4".to_string()));
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::matrices::Vector;
+use crate::messages::Message;
+use crate::random::Rng;
+
+//directed communication graph between agents, an edge from -> to means from can send to to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    links: Vec<Vec<usize>>, //sorted out-neighbours of every agent
+}
+impl Topology {
+    //size agents and no links between them
+    pub fn new(size: usize) -> Topology {
+        Topology { links: vec![vec![]; size] }
+    }
+    pub fn fully_connected(size: usize) -> Topology {
+        let mut topology = Topology::new(size);
+        for from in 0..size {
+            for to in 0..size {
+                topology.link(from, to);
+            }
+        }
+        topology
+    }
+    pub fn ring(size: usize) -> Topology {
+        let mut topology = Topology::new(size);
+        for i in 0..size {
+            topology.link_both(i, (i + 1) % size);
+        }
+        topology
+    }
+    //agent x + y * width talks to the agents above, below, left and right of it
+    pub fn grid(width: usize, height: usize) -> Topology {
+        let mut topology = Topology::new(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                if x + 1 < width {
+                    topology.link_both(x + y * width, x + 1 + y * width);
+                }
+                if y + 1 < height {
+                    topology.link_both(x + y * width, x + (y + 1) * width);
+                }
+            }
+        }
+        topology
+    }
+    //agent 0 is the hub
+    pub fn star(size: usize) -> Topology {
+        let mut topology = Topology::new(size);
+        for i in 1..size {
+            topology.link_both(0, i);
+        }
+        topology
+    }
+    //every agent hears from the k agents closest to it
+    pub fn k_nearest(locations: &[Vector], k: usize) -> Topology {
+        let mut topology = Topology::new(locations.len());
+        for (to, location) in locations.iter().enumerate() {
+            let mut others: Vec<(f64, usize)> = locations.iter().enumerate()
+                .filter(|&(from, _)| from != to)
+                .map(|(from, other)| (Vector::vector_between(location, other).magnitude_squared(), from))
+                .collect();
+            others.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for &(_, from) in others.iter().take(k) {
+                topology.link(from, to);
+            }
+        }
+        topology
+    }
+    //agents closer than radius to each other are linked both ways, like radios with a limited range
+    pub fn within_radius(locations: &[Vector], radius: f64) -> Topology {
+        let mut topology = Topology::new(locations.len());
+        for a in 0..locations.len() {
+            for b in (a + 1)..locations.len() {
+                if Vector::vector_between(&locations[a], &locations[b]).magnitude_squared() < radius.powi(2) {
+                    topology.link_both(a, b);
+                }
+            }
+        }
+        topology
+    }
+    //every pair of agents is linked (both ways) independently with probability p
+    pub fn erdos_renyi(size: usize, p: f64, seed: u64) -> Topology {
+        let mut rng = Rng::new(seed);
+        let mut topology = Topology::new(size);
+        for a in 0..size {
+            for b in (a + 1)..size {
+                if rng.next_f64() < p {
+                    topology.link_both(a, b);
+                }
+            }
+        }
+        topology
+    }
+
+    pub fn size(&self) -> usize {
+        self.links.len()
+    }
+    //agents never link to themselves and repeated links are ignored
+    pub fn link(&mut self, from: usize, to: usize) {
+        assert!(from < self.size() && to < self.size(), "link {} -> {} is outside a topology of {} agents", from, to, self.size());
+        if from == to {
+            return;
+        }
+        if let Err(position) = self.links[from].binary_search(&to) {
+            self.links[from].insert(position, to);
+        }
+    }
+    pub fn link_both(&mut self, a: usize, b: usize) {
+        self.link(a, b);
+        self.link(b, a);
+    }
+    pub fn has_link(&self, from: usize, to: usize) -> bool {
+        self.links[from].binary_search(&to).is_ok()
+    }
+    //the agents that from sends to
+    pub fn neighbours(&self, from: usize) -> &[usize] {
+        &self.links[from]
+    }
+    //how many agents send to to
+    pub fn in_degree(&self, to: usize) -> usize {
+        self.links.iter().filter(|out| out.binary_search(&to).is_ok()).count()
+    }
+    pub fn links(&self) -> Vec<(usize, usize)> {
+        let mut links = vec![];
+        for (from, out) in self.links.iter().enumerate() {
+            for &to in out {
+                links.push((from, to));
+            }
+        }
+        links
+    }
+    //graphviz description of the graph, links going both ways are drawn as a single undirected edge
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph topology {\n");
+        for i in 0..self.size() {
+            dot += &format!("    {};\n", i);
+        }
+        for (from, to) in self.links() {
+            if self.has_link(to, from) {
+                if from < to {
+                    dot += &format!("    {} -> {} [dir=none];\n", from, to);
+                }
+            }
+            else {
+                dot += &format!("    {} -> {};\n", from, to);
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+//what one agent needs to take part in the mesh: a sender to each agent it talks to and its own receiver
+pub struct Endpoint {
+    pub senders: Vec<Sender<Message>>,
+    pub receiver: Receiver<Message>,
+    pub expected: usize, //how many agents send to this one every tick
+}
+//holds a sender to every agent's receiver so that links can be rewired while the agents are running
+#[derive(Clone)]
+pub struct ChannelMesh {
+    senders: Vec<Sender<Message>>,
+}
+impl ChannelMesh {
+    pub fn size(&self) -> usize {
+        self.senders.len()
+    }
+    pub fn senders_for(&self, topology: &Topology, from: usize) -> Vec<Sender<Message>> {
+        topology.neighbours(from).iter().map(|&to| self.senders[to].clone()).collect()
+    }
+}
+pub struct MeshBuilder {
+    topology: Topology,
+}
+impl MeshBuilder {
+    //size agents and no links between them
+    pub fn new(size: usize) -> MeshBuilder {
+        MeshBuilder { topology: Topology::new(size) }
+    }
+    pub fn fully_connected(size: usize) -> MeshBuilder {
+        MeshBuilder { topology: Topology::fully_connected(size) }
+    }
+    pub fn from_topology(topology: Topology) -> MeshBuilder {
+        MeshBuilder { topology }
+    }
+    pub fn size(&self) -> usize {
+        self.topology.size()
+    }
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+    //lets from send to to, agents never get a channel to themselves
+    pub fn link(mut self, from: usize, to: usize) -> MeshBuilder {
+        self.topology.link(from, to);
+        self
+    }
+    pub fn link_both(self, a: usize, b: usize) -> MeshBuilder {
+        self.link(a, b).link(b, a)
+    }
+    pub fn build(self) -> (ChannelMesh, Vec<Endpoint>) {
+        let (senders, receivers): (Vec<Sender<Message>>, Vec<Receiver<Message>>) = (0..self.size()).map(|_| channel()).unzip();
+        let mesh = ChannelMesh { senders };
+        let endpoints = receivers.into_iter().enumerate().map(|(id, receiver)| {
+            Endpoint {
+                senders: mesh.senders_for(&self.topology, id),
+                receiver,
+                expected: self.topology.in_degree(id),
+            }
+        }).collect();
+        (mesh, endpoints)
+    }
+}
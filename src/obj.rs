@@ -0,0 +1,171 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::matrices::Vector;
+use crate::raytracer::scene_objects::{Mesh, Triangle};
+
+//why an obj file could not be read or parsed
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    InvalidNumber { line: usize, value: String },
+    MissingField { line: usize },
+    InvalidIndex { line: usize, index: String },
+}
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "could not read obj file: {}", error),
+            ObjError::InvalidNumber { line, value } => write!(f, "line {}: could not parse '{}' as a number", line, value),
+            ObjError::MissingField { line } => write!(f, "line {}: not enough values", line),
+            ObjError::InvalidIndex { line, index } => write!(f, "line {}: '{}' does not refer to a vertex", line, index),
+        }
+    }
+}
+impl std::error::Error for ObjError {}
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> ObjError {
+        ObjError::Io(error)
+    }
+}
+
+fn parse_vector<'a>(fields: &mut impl Iterator<Item = &'a str>, line: usize) -> Result<Vector, ObjError> {
+    let mut coords = [0.0; 3];
+    for coord in coords.iter_mut() {
+        let field = fields.next().ok_or(ObjError::MissingField { line })?;
+        *coord = field.parse::<f64>().map_err(|_| ObjError::InvalidNumber { line, value: field.to_string() })?;
+    }
+    Ok(Vector::from_vec(coords.to_vec()))
+}
+//obj indices start at 1 and negative ones count back from the latest entry
+fn resolve_index(index: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let invalid = || ObjError::InvalidIndex { line, index: index.to_string() };
+    let value = index.parse::<i64>().map_err(|_| invalid())?;
+    let resolved = if value < 0 { count as i64 + value } else { value - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(invalid());
+    }
+    Ok(resolved as usize)
+}
+
+//loads the geometry of a Wavefront obj file: v, vn and f lines, everything else (textures, groups, materials) is skipped
+pub fn parse_obj(source: &str) -> Result<Mesh, ObjError> {
+    let mut vertices = vec![];
+    let mut normals = vec![];
+    let mut triangles = vec![];
+    for (number, text) in source.lines().enumerate() {
+        let line = number + 1;
+        let mut fields = text.split_whitespace();
+        match fields.next() {
+            Some("v") => vertices.push(parse_vector(&mut fields, line)?),
+            Some("vn") => normals.push(parse_vector(&mut fields, line)?.return_normalised()),
+            Some("f") => {
+                //each corner is v, v/vt, v//vn or v/vt/vn
+                let mut corners = vec![];
+                for corner in fields {
+                    let mut parts = corner.split('/');
+                    let vertex = resolve_index(parts.next().unwrap_or(""), vertices.len(), line)?;
+                    let normal = match parts.nth(1) {
+                        Some(index) if !index.is_empty() => Some(resolve_index(index, normals.len(), line)?),
+                        _ => None
+                    };
+                    corners.push((vertex, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(ObjError::MissingField { line });
+                }
+                //polygons are split into a fan of triangles around their first corner
+                for i in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                    let mut triangle = Triangle::new(vertices[a.0], vertices[b.0], vertices[c.0]);
+                    if let (Some(na), Some(nb), Some(nc)) = (a.1, b.1, c.1) {
+                        triangle.normals = Some([normals[na], normals[nb], normals[nc]]);
+                    }
+                    triangles.push(triangle);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Mesh::new(triangles))
+}
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Mesh, ObjError> {
+    parse_obj(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::SceneObject;
+
+    fn xyz(vector: &Vector) -> (f64, f64, f64) {
+        (vector.x, vector.y, vector.z)
+    }
+    const SQUARE: &str = "v 0 0 0\nv 2 0 0\nv 2 2 0\nv 0 2 0\nvn 0 0 3\n";
+
+    #[test]
+    fn corners_may_carry_texture_and_normal_indices() {
+        let mesh = parse_obj(&format!("{}f 1 2 3\nf 1/1 2/2 3/3\nf 1//1 2//1 3//1\nf 1/7/1 2/7/1 3/7/1\n", SQUARE)).unwrap();
+        let triangles = mesh.triangles();
+        assert_eq!(triangles.len(), 4);
+        for triangle in triangles {
+            assert_eq!([xyz(&triangle.a), xyz(&triangle.b), xyz(&triangle.c)], [(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (2.0, 2.0, 0.0)]);
+        }
+        assert!(triangles[0].normals.is_none());
+        assert!(triangles[1].normals.is_none());
+        for triangle in &triangles[2..] {
+            let normals = triangle.normals.expect("v//vn and v/vt/vn corners carry normals");
+            assert!(normals.iter().all(|normal| xyz(normal) == (0.0, 0.0, 1.0)), "normals are normalised");
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let mesh = parse_obj(&format!("{}f -4 -3 -2\n", SQUARE)).unwrap();
+        let triangle = &mesh.triangles()[0];
+        assert_eq!([xyz(&triangle.a), xyz(&triangle.b), xyz(&triangle.c)], [(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (2.0, 2.0, 0.0)]);
+    }
+
+    #[test]
+    fn polygons_are_split_into_a_fan() {
+        let mesh = parse_obj(&format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+        let corners: Vec<_> = mesh.triangles().iter().map(|triangle| [xyz(&triangle.a), xyz(&triangle.b), xyz(&triangle.c)]).collect();
+        assert_eq!(corners, vec![
+            [(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (2.0, 2.0, 0.0)],
+            [(0.0, 0.0, 0.0), (2.0, 2.0, 0.0), (0.0, 2.0, 0.0)],
+        ]);
+    }
+
+    #[test]
+    fn malformed_lines_report_where_they_are() {
+        match parse_obj("v 0 0 0\nv 1 x 0\n") {
+            Err(ObjError::InvalidNumber { line: 2, value }) => assert_eq!(value, "x"),
+            other => panic!("expected an invalid number, got {:?}", other.err())
+        }
+        assert!(matches!(parse_obj("v 0 0\n"), Err(ObjError::MissingField { line: 1 })));
+        assert!(matches!(parse_obj(&format!("{}f 1 2\n", SQUARE)), Err(ObjError::MissingField { line: 6 })));
+        match parse_obj(&format!("{}f 1 2 5\n", SQUARE)) {
+            Err(ObjError::InvalidIndex { line: 6, index }) => assert_eq!(index, "5"),
+            other => panic!("expected an invalid index, got {:?}", other.err())
+        }
+        assert!(matches!(parse_obj(&format!("{}f 1 2 -5\n", SQUARE)), Err(ObjError::InvalidIndex { line: 6, .. })));
+        assert!(matches!(parse_obj(&format!("{}f 0 1 2\n", SQUARE)), Err(ObjError::InvalidIndex { line: 6, .. })));
+        assert!(matches!(parse_obj(&format!("{}f 1//2 2//1 3//1\n", SQUARE)), Err(ObjError::InvalidIndex { line: 6, .. })));
+    }
+
+    #[test]
+    fn moving_a_mesh_moves_every_triangle_and_its_bounds() {
+        let mut mesh = parse_obj(&format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+        assert_eq!(xyz(&mesh.get_location()), (1.0, 1.0, 0.0));
+        mesh.set_location(&Vector::new(11.0, -4.0, 5.0));
+        assert_eq!(xyz(&mesh.get_location()), (11.0, -4.0, 5.0));
+        let corners: Vec<_> = mesh.triangles().iter().map(|triangle| [xyz(&triangle.a), xyz(&triangle.b), xyz(&triangle.c)]).collect();
+        assert_eq!(corners, vec![
+            [(10.0, -5.0, 5.0), (12.0, -5.0, 5.0), (12.0, -3.0, 5.0)],
+            [(10.0, -5.0, 5.0), (12.0, -3.0, 5.0), (10.0, -3.0, 5.0)],
+        ]);
+        let bounds = mesh.bounding_box().unwrap();
+        assert_eq!((xyz(&bounds.min), xyz(&bounds.max)), ((10.0, -5.0, 5.0), (12.0, -3.0, 5.0)));
+    }
+}
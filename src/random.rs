@@ -0,0 +1,36 @@
+use crate::matrices::Vector;
+
+//small xorshift64* generator so seeded runs can be reproduced without pulling in a dependency
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64
+}
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        //the state must never be zero, so the seed is scrambled first
+        let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        if state == 0 {
+            state = 0x2545_F491_4F6C_DD1D;
+        }
+        Rng { state }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    //uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+    pub fn unit_vector(&mut self) -> Vector {
+        let z = self.range(-1.0, 1.0);
+        let phi = self.range(0.0, 2.0 * std::f64::consts::PI);
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vector::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
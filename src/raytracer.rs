@@ -3,16 +3,45 @@ pub trait SceneObject: Send {
     fn get_location(&self) -> Vector;
     fn set_location(&mut self, goto: &Vector) -> ();
     fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData>;
+    fn material(&self) -> Material;
+    //box the object fits in, None for objects without bounds such as planes
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        None
+    }
+    //whether the object encloses a volume a ray can be inside of, in which case intersections gives outward normals
+    fn has_inside(&self) -> bool {
+        false
+    }
+    //every point where the ray crosses the surface, nearest first, with normals pointing out of the object
+    //so that entries and exits can be told apart; objects without an inside only report the nearest hit
+    fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+        self.intersection(ray, starting_point).into_iter().collect()
+    }
 }
 pub mod scene_objects {
+    use std::collections::HashMap;
     use crate::matrices::Vector;
-    use crate::raytracer::{IntersectionData, SceneObject};
+    use crate::raytracer::{BoundingBox, IntersectionData, Material, SceneObject};
 
     pub struct Sphere {
         pub radius: f64,
-        pub location: Vector
+        pub location: Vector,
+        pub material: Material
     }
     impl SceneObject for Sphere {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let extent = Vector::new(self.radius, self.radius, self.radius);
+            Some(BoundingBox::around(&self.location, &extent))
+        }
+
         fn get_location(&self) -> Vector {
             self.location
         }
@@ -22,37 +51,876 @@ pub mod scene_objects {
         }
 
         fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
             let det = (2.0 * (starting_point.x - self.location.x) * ray.x + 2.0 * (starting_point.y - self.location.y) * ray.y + 2.0 * (starting_point.z - self.location.z) * ray.z).powi(2)- 4.0 * (ray.x.powi(2) + ray.y.powi(2) + ray.z.powi(2)) * ((starting_point.x - self.location.x).powi(2)+ (starting_point.y - self.location.y).powi(2) + (starting_point.z - self.location.z).powi(2) - self.radius.powi(2));
             if det < 0.0 {
-                return None;
+                return vec![];
             }
-            let t;
             let non_det = - 2.0 * (ray.x * (starting_point.x - self.location.x) + ray.y * (starting_point.y - self.location.y) + ray.z * (starting_point.z - self.location.z));
             let denominator = 2.0 * ray.magnitude_squared();
-            if non_det - det.powf(0.5) > 0.0 {
-                t = (non_det - det.powf(0.5)) / denominator;
+            let mut to_return = vec![];
+            for t in [(non_det - det.powf(0.5)) / denominator, (non_det + det.powf(0.5)) / denominator] {
+                if t > 0.0 {
+                    let location = starting_point.return_plus(&ray.return_multiply(t));
+                    let normal = Vector::vector_between(&self.location, &location).return_normalised();
+                    to_return.push(IntersectionData::new(location, normal, t * ray.magnitude()));
+                }
+            }
+            to_return
+        }
+
+    }
+
+    //infinite plane through point, the normal returned always faces the side the ray comes from
+    pub struct Plane {
+        pub point: Vector,
+        pub normal: Vector,
+        pub material: Material
+    }
+    impl SceneObject for Plane {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn get_location(&self) -> Vector {
+            self.point
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.point = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            let normal = self.normal.return_normalised();
+            let denominator = Vector::dot(ray, &normal);
+            if denominator.abs() < 1e-12 {
+                return None;
+            }
+            let t = Vector::dot(&Vector::vector_between(starting_point, &self.point), &normal) / denominator;
+            if t <= 0.0 {
+                return None;
+            }
+            let location = starting_point.return_plus(&ray.return_multiply(t));
+            let facing = if denominator > 0.0 { normal.return_multiply(-1.0) } else { normal };
+            Some(IntersectionData::new(location, facing, t * ray.magnitude()))
+        }
+    }
+
+    //slab test against the box from min to max, gives the ray parameter, the axis of the face hit and which way it faces
+    //for the entry and exit ahead of the ray
+    fn slab_intersections(starting_point: &[f64; 3], ray: &[f64; 3], min: &[f64; 3], max: &[f64; 3]) -> Vec<(f64, usize, f64)> {
+        let mut near = (f64::NEG_INFINITY, 0, 0.0);
+        let mut far = (f64::INFINITY, 0, 0.0);
+        for axis in 0..3 {
+            if ray[axis].abs() < 1e-12 {
+                if starting_point[axis] < min[axis] || starting_point[axis] > max[axis] {
+                    return vec![];
+                }
+                continue;
+            }
+            let to_min = (min[axis] - starting_point[axis]) / ray[axis];
+            let to_max = (max[axis] - starting_point[axis]) / ray[axis];
+            let (entry, exit) = if to_min < to_max { ((to_min, -1.0), (to_max, 1.0)) } else { ((to_max, 1.0), (to_min, -1.0)) };
+            if entry.0 > near.0 {
+                near = (entry.0, axis, entry.1);
+            }
+            if exit.0 < far.0 {
+                far = (exit.0, axis, exit.1);
+            }
+        }
+        if near.0 > far.0 || far.0 <= 0.0 {
+            return vec![];
+        }
+        //a ray starting inside the box only has the exit ahead of it
+        if near.0 > 0.0 { vec![near, far] } else { vec![far] }
+    }
+    fn as_array(v: &Vector) -> [f64; 3] {
+        [v.x, v.y, v.z]
+    }
+
+    //axis aligned box between the corners min and max
+    pub struct AaBox {
+        pub min: Vector,
+        pub max: Vector,
+        pub material: Material
+    }
+    impl SceneObject for AaBox {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            Some(BoundingBox::new(self.min, self.max))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.min.return_plus(&self.max).return_multiply(0.5)
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            let shift = Vector::vector_between(&self.get_location(), goto);
+            self.min.plus(&shift);
+            self.max.plus(&shift);
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            slab_intersections(&as_array(starting_point), &as_array(ray), &as_array(&self.min), &as_array(&self.max)).into_iter().map(|(t, axis, side)| {
+                let mut normal = [0.0; 3];
+                normal[axis] = side;
+                let location = starting_point.return_plus(&ray.return_multiply(t));
+                IntersectionData::new(location, Vector::new(normal[0], normal[1], normal[2]), t * ray.magnitude())
+            }).collect()
+        }
+    }
+
+    fn facing_back(normal: &Vector, ray: &Vector) -> Vector {
+        if Vector::dot(normal, ray) > 0.0 { normal.return_multiply(-1.0) } else { *normal }
+    }
+    //Möller–Trumbore, gives the ray parameter and the barycentric weights of b and c
+    fn triangle_hit(a: &Vector, b: &Vector, c: &Vector, ray: &Vector, starting_point: &Vector) -> Option<(f64, f64, f64)> {
+        let edge_one = Vector::vector_between(a, b);
+        let edge_two = Vector::vector_between(a, c);
+        let p = Vector::cross(ray, &edge_two);
+        let determinant = Vector::dot(&edge_one, &p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let to_start = Vector::vector_between(a, starting_point);
+        let u = Vector::dot(&to_start, &p) / determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = Vector::cross(&to_start, &edge_one);
+        let v = Vector::dot(ray, &q) / determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = Vector::dot(&edge_two, &q) / determinant;
+        if t <= 0.0 {
+            return None;
+        }
+        Some((t, u, v))
+    }
+
+    //single triangle, vertex normals (e.g. from an obj file) are interpolated across it when given
+    #[derive(Debug, Clone)]
+    pub struct Triangle {
+        pub a: Vector,
+        pub b: Vector,
+        pub c: Vector,
+        pub normals: Option<[Vector; 3]>,
+        pub material: Material
+    }
+    impl Triangle {
+        pub fn new(a: Vector, b: Vector, c: Vector) -> Triangle {
+            Triangle { a, b, c, normals: None, material: Material::default() }
+        }
+        fn translate(&mut self, by: &Vector) {
+            self.a.plus(by);
+            self.b.plus(by);
+            self.c.plus(by);
+        }
+        //normal follows the winding (counter-clockwise seen from the front), callers decide which way it should face
+        fn hit(&self, ray: &Vector, starting_point: &Vector) -> Option<(f64, Vector)> {
+            let (t, u, v) = triangle_hit(&self.a, &self.b, &self.c, ray, starting_point)?;
+            let normal = match &self.normals {
+                Some([na, nb, nc]) => na.return_multiply(1.0 - u - v).return_plus(&nb.return_multiply(u)).return_plus(&nc.return_multiply(v)).return_normalised(),
+                None => Vector::cross(&Vector::vector_between(&self.a, &self.b), &Vector::vector_between(&self.a, &self.c)).return_normalised()
+            };
+            Some((t, normal))
+        }
+    }
+    impl SceneObject for Triangle {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            Some(BoundingBox::containing(&[self.a, self.b, self.c]))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.a.return_plus(&self.b).return_plus(&self.c).return_multiply(1.0 / 3.0)
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            let shift = Vector::vector_between(&self.get_location(), goto);
+            self.translate(&shift);
+        }
+
+        //both sides of a lone triangle can be lit, so the normal always faces back along the ray
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            let (t, normal) = self.hit(ray, starting_point)?;
+            let location = starting_point.return_plus(&ray.return_multiply(t));
+            Some(IntersectionData::new(location, facing_back(&normal, ray), t * ray.magnitude()))
+        }
+    }
+
+    //a body made of triangles, moving it moves every triangle so it keeps its shape
+    pub struct Mesh {
+        pub material: Material,
+        triangles: Vec<Triangle>,
+        location: Vector,
+        min: Vector,
+        max: Vector,
+        closed: bool
+    }
+    impl Mesh {
+        //the mesh's location starts at the centre of its bounding box
+        pub fn new(triangles: Vec<Triangle>) -> Mesh {
+            let closed = Mesh::encloses_volume(&triangles);
+            let mut mesh = Mesh { material: Material::default(), triangles, location: Vector::origin(), min: Vector::origin(), max: Vector::origin(), closed };
+            mesh.update_bounds();
+            mesh.location = mesh.min.return_plus(&mesh.max).return_multiply(0.5);
+            mesh
+        }
+        //closed and consistently wound: every edge is shared by exactly two triangles that run along it in opposite directions
+        fn encloses_volume(triangles: &[Triangle]) -> bool {
+            let key = |vertex: &Vector| [vertex.x.to_bits(), vertex.y.to_bits(), vertex.z.to_bits()];
+            let mut edges: HashMap<([u64; 3], [u64; 3]), usize> = HashMap::new();
+            for triangle in triangles {
+                for (from, to) in [(&triangle.a, &triangle.b), (&triangle.b, &triangle.c), (&triangle.c, &triangle.a)] {
+                    *edges.entry((key(from), key(to))).or_insert(0) += 1;
+                }
+            }
+            !triangles.is_empty() && edges.iter().all(|((from, to), count)| *count == 1 && edges.get(&(*to, *from)) == Some(&1))
+        }
+        //closed meshes have an inside, so transparent ones refract like any other solid; open ones are thin sheets
+        pub fn is_closed(&self) -> bool {
+            self.closed
+        }
+        pub fn triangles(&self) -> &Vec<Triangle> {
+            &self.triangles
+        }
+        //grows or shrinks the mesh about its location, handy for models authored in other units
+        pub fn scale(&mut self, factor: f64) {
+            let location = self.location;
+            let scale_point = |point: &Vector| location.return_plus(&Vector::vector_between(&location, point).return_multiply(factor));
+            for triangle in self.triangles.iter_mut() {
+                triangle.a = scale_point(&triangle.a);
+                triangle.b = scale_point(&triangle.b);
+                triangle.c = scale_point(&triangle.c);
+            }
+            self.update_bounds();
+        }
+        fn update_bounds(&mut self) {
+            let mut min = Vector::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+            let mut max = Vector::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+            for triangle in &self.triangles {
+                for vertex in [&triangle.a, &triangle.b, &triangle.c] {
+                    min = Vector::new(min.x.min(vertex.x), min.y.min(vertex.y), min.z.min(vertex.z));
+                    max = Vector::new(max.x.max(vertex.x), max.y.max(vertex.y), max.z.max(vertex.z));
+                }
+            }
+            if self.triangles.is_empty() {
+                min = Vector::origin();
+                max = Vector::origin();
+            }
+            self.min = min;
+            self.max = max;
+        }
+    }
+    impl SceneObject for Mesh {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            self.closed
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            if self.triangles.is_empty() {
+                return None;
+            }
+            Some(BoundingBox::new(self.min, self.max))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.location
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            let shift = Vector::vector_between(&self.location, goto);
+            for triangle in self.triangles.iter_mut() {
+                triangle.translate(&shift);
             }
-            else if non_det + det.powf(0.5) > 0.0 {
-                t = (non_det + det.powf(0.5)) / denominator;
+            self.min.plus(&shift);
+            self.max.plus(&shift);
+            self.location = *goto;
+        }
+
+        //like a lone triangle the nearest hit faces back along the ray, so open or inconsistently wound meshes still light up
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            let nearest = self.intersections(ray, starting_point).into_iter().next()?;
+            Some(IntersectionData::new(nearest.location, facing_back(&nearest.normal, ray), nearest.distance))
+        }
+
+        //for closed meshes wound counter-clockwise seen from outside, as obj files are, these normals point outwards
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            //skip every triangle when the ray misses the mesh's bounding box
+            if slab_intersections(&as_array(starting_point), &as_array(ray), &as_array(&self.min), &as_array(&self.max)).is_empty() {
+                return vec![];
+            }
+            sorted_hits(self.triangles.iter().filter_map(|triangle| triangle.hit(ray, starting_point)).collect(), ray, starting_point)
+        }
+    }
+
+    //the hits ahead of the ray out of a list of (t, outward normal) candidates, nearest first
+    fn sorted_hits(mut hits: Vec<(f64, Vector)>, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+        hits.retain(|(t, _)| *t > 0.0);
+        hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        hits.into_iter().map(|(t, normal)| {
+            let location = starting_point.return_plus(&ray.return_multiply(t));
+            IntersectionData::new(location, normal, t * ray.magnitude())
+        }).collect()
+    }
+    //roots of a t^2 + b t + c, smallest first
+    fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+        if a.abs() < 1e-12 {
+            if b.abs() < 1e-12 {
+                return vec![];
+            }
+            return vec![-c / b];
+        }
+        let det = b * b - 4.0 * a * c;
+        if det < 0.0 {
+            return vec![];
+        }
+        let (first, second) = ((-b - det.sqrt()) / (2.0 * a), (-b + det.sqrt()) / (2.0 * a));
+        if first < second { vec![first, second] } else { vec![second, first] }
+    }
+    //half size along x, y and z of a circle of radius lying across axis
+    fn round_extent(axis: &Vector, radius: f64) -> Vector {
+        let across = |component: f64| radius * (1.0 - component.powi(2)).max(0.0).sqrt();
+        Vector::new(across(axis.x), across(axis.y), across(axis.z))
+    }
+    //two unit vectors perpendicular to axis and to each other
+    fn perpendicular_basis(axis: &Vector) -> (Vector, Vector) {
+        let helper = if axis.x.abs() < 0.9 { Vector::new(1.0, 0.0, 0.0) } else { Vector::new(0.0, 1.0, 0.0) };
+        let u = Vector::cross(axis, &helper).return_normalised();
+        let v = Vector::cross(axis, &u);
+        (u, v)
+    }
+
+    //flat round disc, the normal returned faces the side the ray comes from
+    pub struct Disc {
+        pub centre: Vector,
+        pub normal: Vector,
+        pub radius: f64,
+        pub material: Material
+    }
+    impl SceneObject for Disc {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let normal = self.normal.return_normalised();
+            Some(BoundingBox::around(&self.centre, &round_extent(&normal, self.radius)))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.centre
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.centre = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            let normal = self.normal.return_normalised();
+            let denominator = Vector::dot(ray, &normal);
+            if denominator.abs() < 1e-12 {
+                return None;
             }
-            else {
+            let t = Vector::dot(&Vector::vector_between(starting_point, &self.centre), &normal) / denominator;
+            let location = starting_point.return_plus(&ray.return_multiply(t));
+            if t <= 0.0 || Vector::vector_between(&self.centre, &location).magnitude_squared() > self.radius.powi(2) {
                 return None;
             }
-            let location = ray.return_multiply(t);
-            let normal = Vector::vector_between(&self.location, &location).return_normalised();
-            Some(IntersectionData::new(location, normal, t * ray.magnitude()))
+            let facing = if denominator > 0.0 { normal.return_multiply(-1.0) } else { normal };
+            Some(IntersectionData::new(location, facing, t * ray.magnitude()))
+        }
+    }
+
+    //cylinder closed at both ends, centred on centre with its length running along axis
+    pub struct Cylinder {
+        pub centre: Vector,
+        pub axis: Vector,
+        pub radius: f64,
+        pub height: f64,
+        pub material: Material
+    }
+    impl Cylinder {
+        fn hits(&self, ray: &Vector, starting_point: &Vector) -> Vec<(f64, Vector)> {
+            let axis = self.axis.return_normalised();
+            let offset = Vector::vector_between(&self.centre, starting_point);
+            let (ray_along, offset_along) = (Vector::dot(ray, &axis), Vector::dot(&offset, &axis));
+            let ray_across = Vector::vector_between(&axis.return_multiply(ray_along), ray);
+            let offset_across = Vector::vector_between(&axis.return_multiply(offset_along), &offset);
+            let half = self.height / 2.0;
+            let mut hits = vec![];
+            let a = ray_across.magnitude_squared();
+            if a > 1e-12 {
+                for t in solve_quadratic(a, 2.0 * Vector::dot(&ray_across, &offset_across), offset_across.magnitude_squared() - self.radius.powi(2)) {
+                    if (offset_along + t * ray_along).abs() <= half {
+                        hits.push((t, offset_across.return_plus(&ray_across.return_multiply(t)).return_normalised()));
+                    }
+                }
+            }
+            if ray_along.abs() > 1e-12 {
+                for side in [-1.0, 1.0] {
+                    let t = (side * half - offset_along) / ray_along;
+                    if offset_across.return_plus(&ray_across.return_multiply(t)).magnitude_squared() <= self.radius.powi(2) {
+                        hits.push((t, axis.return_multiply(side)));
+                    }
+                }
+            }
+            hits
+        }
+    }
+    impl SceneObject for Cylinder {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let axis = self.axis.return_normalised();
+            let along = Vector::new(axis.x.abs(), axis.y.abs(), axis.z.abs()).return_multiply(self.height / 2.0);
+            Some(BoundingBox::around(&self.centre, &along.return_plus(&round_extent(&axis, self.radius))))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.centre
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.centre = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            sorted_hits(self.hits(ray, starting_point), ray, starting_point)
+        }
+    }
+
+    //cone with a closed round base of radius at base, narrowing to a point height along axis
+    pub struct Cone {
+        pub base: Vector,
+        pub axis: Vector,
+        pub radius: f64,
+        pub height: f64,
+        pub material: Material
+    }
+    impl Cone {
+        fn hits(&self, ray: &Vector, starting_point: &Vector) -> Vec<(f64, Vector)> {
+            //measured from the tip, down towards the base
+            let down = self.axis.return_normalised().return_multiply(-1.0);
+            let tip = self.base.return_plus(&down.return_multiply(-self.height));
+            let offset = Vector::vector_between(&tip, starting_point);
+            let cos_squared = self.height.powi(2) / (self.height.powi(2) + self.radius.powi(2));
+            let (ray_down, offset_down) = (Vector::dot(ray, &down), Vector::dot(&offset, &down));
+            let a = ray_down.powi(2) - cos_squared * ray.magnitude_squared();
+            let b = 2.0 * (ray_down * offset_down - cos_squared * Vector::dot(ray, &offset));
+            let c = offset_down.powi(2) - cos_squared * offset.magnitude_squared();
+            let mut hits = vec![];
+            for t in solve_quadratic(a, b, c) {
+                let point = offset.return_plus(&ray.return_multiply(t));
+                let along = Vector::dot(&point, &down);
+                //the equation also describes the mirrored cone above the tip
+                if along >= 0.0 && along <= self.height {
+                    let normal = Vector::vector_between(&down.return_multiply(along), &point.return_multiply(cos_squared));
+                    hits.push((t, normal.return_normalised()));
+                }
+            }
+            if ray_down.abs() > 1e-12 {
+                let t = (self.height - offset_down) / ray_down;
+                let point = offset.return_plus(&ray.return_multiply(t));
+                if Vector::vector_between(&down.return_multiply(self.height), &point).magnitude_squared() <= self.radius.powi(2) {
+                    hits.push((t, down));
+                }
+            }
+            hits
+        }
+    }
+    impl SceneObject for Cone {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let axis = self.axis.return_normalised();
+            let tip = self.base.return_plus(&axis.return_multiply(self.height));
+            Some(BoundingBox::around(&self.base, &round_extent(&axis, self.radius)).merge(&BoundingBox::new(tip, tip)))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.base
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.base = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            sorted_hits(self.hits(ray, starting_point), ray, starting_point)
+        }
+    }
+
+    //ring shaped torus around centre, major_radius from the centre to the middle of the tube and minor_radius across the tube
+    pub struct Torus {
+        pub centre: Vector,
+        pub axis: Vector,
+        pub major_radius: f64,
+        pub minor_radius: f64,
+        pub material: Material
+    }
+    impl Torus {
+        fn hits(&self, ray: &Vector, starting_point: &Vector) -> Vec<(f64, Vector)> {
+            let axis = self.axis.return_normalised();
+            let (u, v) = perpendicular_basis(&axis);
+            let to_local = |p: &Vector| Vector::new(Vector::dot(p, &u), Vector::dot(p, &v), Vector::dot(p, &axis));
+            let origin = to_local(&Vector::vector_between(&self.centre, starting_point));
+            let direction = to_local(ray);
+            let (big, small) = (self.major_radius, self.minor_radius);
+            let surface = |t: f64| {
+                let p = origin.return_plus(&direction.return_multiply(t));
+                (p.magnitude_squared() + big * big - small * small).powi(2) - 4.0 * big * big * (p.x * p.x + p.y * p.y)
+            };
+            //the quartic is solved numerically: walk the stretch of ray inside the bounding sphere looking for sign changes
+            let bounds = solve_quadratic(direction.magnitude_squared(), 2.0 * Vector::dot(&origin, &direction), origin.magnitude_squared() - (big + small).powi(2));
+            if bounds.len() < 2 {
+                return vec![];
+            }
+            let (start, end) = (bounds[0].max(0.0), bounds[1]);
+            if end <= start {
+                return vec![];
+            }
+            let length = (end - start) * direction.magnitude();
+            let steps = ((4.0 * length / small).ceil() as usize).clamp(16, 1000);
+            let step = (end - start) / steps as f64;
+            let mut hits = vec![];
+            let mut previous = surface(start);
+            for i in 1..=steps {
+                let (mut low, mut high) = (start + step * (i - 1) as f64, start + step * i as f64);
+                let current = surface(high);
+                if previous.signum() != current.signum() {
+                    let low_sign = previous.signum();
+                    for _ in 0..60 {
+                        let middle = (low + high) / 2.0;
+                        if surface(middle).signum() == low_sign { low = middle; } else { high = middle; }
+                    }
+                    let t = (low + high) / 2.0;
+                    let p = origin.return_plus(&direction.return_multiply(t));
+                    let ring = Vector::new(p.x, p.y, 0.0).return_normalised().return_multiply(big);
+                    let local_normal = Vector::vector_between(&ring, &p).return_normalised();
+                    let normal = u.return_multiply(local_normal.x).return_plus(&v.return_multiply(local_normal.y)).return_plus(&axis.return_multiply(local_normal.z));
+                    hits.push((t, normal));
+                }
+                previous = current;
+            }
+            hits
+        }
+    }
+    impl SceneObject for Torus {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let axis = self.axis.return_normalised();
+            let tube = Vector::new(self.minor_radius, self.minor_radius, self.minor_radius);
+            Some(BoundingBox::around(&self.centre, &round_extent(&axis, self.major_radius).return_plus(&tube)))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.centre
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.centre = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            sorted_hits(self.hits(ray, starting_point), ray, starting_point)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum CsgOperation {
+        Union,
+        Intersection,
+        Difference, //left with right cut out of it
+    }
+    impl CsgOperation {
+        fn inside(&self, in_left: bool, in_right: bool) -> bool {
+            match self {
+                CsgOperation::Union => in_left || in_right,
+                CsgOperation::Intersection => in_left && in_right,
+                CsgOperation::Difference => in_left && !in_right,
+            }
+        }
+    }
+    //boolean combination of two solids, both need to report their exits as well as their entries through intersections
+    pub struct Csg {
+        pub operation: CsgOperation,
+        pub left: Box<dyn SceneObject + Send + Sync>,
+        pub right: Box<dyn SceneObject + Send + Sync>
+    }
+    impl Csg {
+        pub fn union(left: Box<dyn SceneObject + Send + Sync>, right: Box<dyn SceneObject + Send + Sync>) -> Csg {
+            Csg { operation: CsgOperation::Union, left, right }
+        }
+        pub fn intersection(left: Box<dyn SceneObject + Send + Sync>, right: Box<dyn SceneObject + Send + Sync>) -> Csg {
+            Csg { operation: CsgOperation::Intersection, left, right }
+        }
+        pub fn difference(left: Box<dyn SceneObject + Send + Sync>, right: Box<dyn SceneObject + Send + Sync>) -> Csg {
+            Csg { operation: CsgOperation::Difference, left, right }
+        }
+    }
+    impl SceneObject for Csg {
+        //hits keep the material of the operand they came from, this is only used when one is missing
+        fn material(&self) -> Material {
+            self.left.material()
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        //the result never reaches outside the left operand, or outside either one for an intersection
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            match self.operation {
+                CsgOperation::Union => Some(self.left.bounding_box()?.merge(&self.right.bounding_box()?)),
+                CsgOperation::Intersection => match (self.left.bounding_box(), self.right.bounding_box()) {
+                    (Some(left), Some(right)) => Some(left.overlap(&right)),
+                    (left, right) => left.or(right)
+                },
+                CsgOperation::Difference => self.left.bounding_box()
+            }
+        }
+
+        //the combination is placed by its left operand, the right one keeps its offset from it
+        fn get_location(&self) -> Vector {
+            self.left.get_location()
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            let shift = Vector::vector_between(&self.left.get_location(), goto);
+            self.left.set_location(goto);
+            let right_goto = self.right.get_location().return_plus(&shift);
+            self.right.set_location(&right_goto);
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            let entering = |hit: &IntersectionData| Vector::dot(ray, &hit.normal) < 0.0;
+            let with_material = |hit: IntersectionData, object: &(dyn SceneObject + Send + Sync)| {
+                let material = hit.material.unwrap_or_else(|| object.material());
+                hit.with_material(material)
+            };
+            let left: Vec<IntersectionData> = self.left.intersections(ray, starting_point).into_iter().map(|hit| with_material(hit, self.left.as_ref())).collect();
+            let right: Vec<IntersectionData> = self.right.intersections(ray, starting_point).into_iter().map(|hit| with_material(hit, self.right.as_ref())).collect();
+            //the ray starts inside an operand when the first crossing ahead of it is an exit
+            let mut in_left = left.first().is_some_and(|hit| !entering(hit));
+            let mut in_right = right.first().is_some_and(|hit| !entering(hit));
+            let mut hits: Vec<(bool, IntersectionData)> = left.into_iter().map(|hit| (true, hit)).chain(right.into_iter().map(|hit| (false, hit))).collect();
+            hits.sort_by(|a, b| a.1.distance.partial_cmp(&b.1.distance).unwrap());
+            let mut to_return = vec![];
+            for (from_left, hit) in hits {
+                let was_inside = self.operation.inside(in_left, in_right);
+                if from_left {
+                    in_left = entering(&hit);
+                }
+                else {
+                    in_right = entering(&hit);
+                }
+                if was_inside != self.operation.inside(in_left, in_right) {
+                    //surfaces of the part cut away face into the hole
+                    if !from_left && self.operation == CsgOperation::Difference {
+                        let flipped = IntersectionData::new(hit.location, hit.normal.return_multiply(-1.0), hit.distance);
+                        to_return.push(match hit.material { Some(material) => flipped.with_material(material), None => flipped });
+                    }
+                    else {
+                        to_return.push(hit);
+                    }
+                }
+            }
+            to_return
         }
+    }
 
+    //box of size 2 * half_extents centred on centre and rotated so its local x and y axes point along x_axis and y_axis
+    pub struct Cuboid {
+        pub centre: Vector,
+        pub half_extents: Vector,
+        pub material: Material,
+        axes: [Vector; 3]
+    }
+    impl Cuboid {
+        pub fn new(centre: Vector, half_extents: Vector, x_axis: &Vector, y_axis: &Vector) -> Cuboid {
+            let u = x_axis.return_normalised();
+            let w = Vector::cross(&u, y_axis).return_normalised();
+            let v = Vector::cross(&w, &u);
+            Cuboid { centre, half_extents, material: Material::default(), axes: [u, v, w] }
+        }
+        pub fn axes(&self) -> [Vector; 3] {
+            self.axes
+        }
+    }
+    impl SceneObject for Cuboid {
+        fn material(&self) -> Material {
+            self.material
+        }
+
+        fn has_inside(&self) -> bool {
+            true
+        }
+
+        fn bounding_box(&self) -> Option<BoundingBox> {
+            let mut extent = Vector::origin();
+            for (axis, half) in self.axes.iter().zip([self.half_extents.x, self.half_extents.y, self.half_extents.z]) {
+                extent.plus(&Vector::new(axis.x.abs(), axis.y.abs(), axis.z.abs()).return_multiply(half));
+            }
+            Some(BoundingBox::around(&self.centre, &extent))
+        }
+
+        fn get_location(&self) -> Vector {
+            self.centre
+        }
+
+        fn set_location(&mut self, goto: &Vector) {
+            self.centre = *goto;
+        }
+
+        fn intersection(&self, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+            self.intersections(ray, starting_point).into_iter().next()
+        }
+
+        fn intersections(&self, ray: &Vector, starting_point: &Vector) -> Vec<IntersectionData> {
+            //work in the box's own frame where it is axis aligned, the rotation keeps t unchanged
+            let offset = Vector::vector_between(&self.centre, starting_point);
+            let local_start = [Vector::dot(&offset, &self.axes[0]), Vector::dot(&offset, &self.axes[1]), Vector::dot(&offset, &self.axes[2])];
+            let local_ray = [Vector::dot(ray, &self.axes[0]), Vector::dot(ray, &self.axes[1]), Vector::dot(ray, &self.axes[2])];
+            let max = as_array(&self.half_extents);
+            let min = [-max[0], -max[1], -max[2]];
+            slab_intersections(&local_start, &local_ray, &min, &max).into_iter().map(|(t, axis, side)| {
+                let location = starting_point.return_plus(&ray.return_multiply(t));
+                IntersectionData::new(location, self.axes[axis].return_multiply(side), t * ray.magnitude())
+            }).collect()
+        }
+    }
+}
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Vector,
+    pub max: Vector
+}
+impl BoundingBox {
+    pub fn new(min: Vector, max: Vector) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+    pub fn around(centre: &Vector, extent: &Vector) -> BoundingBox {
+        BoundingBox::new(Vector::vector_between(extent, centre), centre.return_plus(extent))
+    }
+    pub fn containing(points: &[Vector]) -> BoundingBox {
+        let mut to_return = BoundingBox::new(points[0], points[0]);
+        for point in &points[1..] {
+            to_return = to_return.merge(&BoundingBox::new(*point, *point));
+        }
+        to_return
+    }
+    pub fn merge(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            Vector::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            Vector::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        )
+    }
+    pub fn overlap(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            Vector::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y), self.min.z.max(other.min.z)),
+            Vector::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y), self.max.z.min(other.max.z)),
+        )
+    }
+    pub fn centre(&self) -> Vector {
+        self.min.return_plus(&self.max).return_multiply(0.5)
+    }
+    //ray parameter at which the ray enters the box (0 when it starts inside), None if it misses
+    pub fn entry(&self, ray: &Vector, starting_point: &Vector) -> Option<f64> {
+        let (mut near, mut far) = (0.0f64, f64::INFINITY);
+        for (start, direction, min, max) in [
+            (starting_point.x, ray.x, self.min.x, self.max.x),
+            (starting_point.y, ray.y, self.min.y, self.max.y),
+            (starting_point.z, ray.z, self.min.z, self.max.z),
+        ] {
+            if direction.abs() < 1e-12 {
+                if start < min || start > max {
+                    return None;
+                }
+                continue;
+            }
+            let (to_min, to_max) = ((min - start) / direction, (max - start) / direction);
+            near = near.max(to_min.min(to_max));
+            far = far.min(to_min.max(to_max));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
     }
 }
 pub struct IntersectionData {
     location: Vector,
     normal: Vector,
-    pub(crate) distance: f64
+    pub(crate) distance: f64,
+    material: Option<Material>, //left empty by most objects, the scene fills it in from the object that was hit
+    solid: bool //set by the scene when the object hit has an inside and normal points out of it
 }
 impl IntersectionData {
     pub fn new(location: Vector, normal: Vector, distance: f64) -> IntersectionData {
-        IntersectionData { location, normal, distance}
+        IntersectionData { location, normal, distance, material: None, solid: false }
+    }
+    pub fn with_material(mut self, material: Material) -> IntersectionData {
+        self.material = Some(material);
+        self
     }
 
     pub fn location(&self) -> Vector {
@@ -66,117 +934,465 @@ impl IntersectionData {
     pub fn distance(&self) -> f64 {
         self.distance
     }
+
+    pub fn material(&self) -> Material {
+        self.material.unwrap_or_default()
+    }
 }
 pub mod scene {
-    use std::sync::{Arc, Mutex};
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
     use crate::matrices::Vector;
-    use crate::raytracer::{Colour, IntersectionData, SceneObject};
+    use crate::random::Rng;
+    use crate::raytracer::{BoundingBox, Colour, IntersectionData, Rgb, SceneObject, ToneMapping};
 
+    //look-at camera: the frame is centred on target, with up pointing to the top of the frame
     pub struct Camera {
-        pub direction: Vector,
-        pub location: Vector
+        pub position: Vector,
+        pub target: Vector,
+        pub up: Vector,
+        pub vertical_fov: f64, //degrees
+        pub aspect: f64 //frame width over frame height, normally Screen::aspect
+    }
+    impl Camera {
+        pub fn look_at(position: Vector, target: Vector, up: Vector, vertical_fov: f64, aspect: f64) -> Camera {
+            Camera { position, target, up, vertical_fov, aspect }
+        }
+        pub fn direction(&self) -> Vector {
+            Vector::vector_between(&self.position, &self.target).return_normalised()
+        }
+        //unit vectors to the right of, to the top of and into the frame
+        fn basis(&self) -> (Vector, Vector, Vector) {
+            let forward = self.direction();
+            let mut right = Vector::cross(&forward, &self.up);
+            //looking along up leaves no way to tell which way is up, so borrow another axis
+            if right.magnitude() < 1e-9 {
+                let fallback = if forward.z.abs() < 0.9 { Vector::new(0.0, 0.0, 1.0) } else { Vector::new(0.0, 1.0, 0.0) };
+                right = Vector::cross(&forward, &fallback);
+            }
+            let right = right.return_normalised();
+            (right, Vector::cross(&right, &forward), forward)
+        }
+        fn half_extents(&self) -> (f64, f64) {
+            let half_height = (self.vertical_fov.to_radians() / 2.0).tan();
+            (half_height * self.aspect, half_height)
+        }
+        //ray through the frame at (x, y), both running from -1 to 1, left to right and top to bottom
+        pub fn ray(&self, x: f64, y: f64) -> Vector {
+            let (right, up, forward) = self.basis();
+            let (half_width, half_height) = self.half_extents();
+            forward.return_plus(&right.return_multiply(x * half_width)).return_plus(&up.return_multiply(-y * half_height))
+        }
+        //where point shows up in the frame in the same coordinates as ray, or None if it is behind the camera
+        pub fn frame_position(&self, point: &Vector) -> Option<(f64, f64)> {
+            let (right, up, forward) = self.basis();
+            let (half_width, half_height) = self.half_extents();
+            let offset = Vector::vector_between(&self.position, point);
+            let depth = Vector::dot(&offset, &forward);
+            if depth <= 0.0 {
+                return None;
+            }
+            Some((Vector::dot(&offset, &right) / (depth * half_width), -Vector::dot(&offset, &up) / (depth * half_height)))
+        }
     }
     pub struct Screen {
-        pub distance: f64,
         pub height: i64,
         pub width: i64
     }
 
     impl Screen {
-        fn points_from_camera(&self, cam: &Camera) -> Vec<Vector> {
-            let mut to_return = vec![];
-            let rotation_matrix = Vector::three_rotation_matrix_between(&Vector::new(0.0, 0.0, -1.0), &cam.direction);
-            for y in -self.height..self.height {
-                for x in -self.width..self.width {
-                    //let a = Vector::new(x as f64, y as f64, -self.distance);
-                    to_return.push(Vector::new(x as f64, y as f64, -self.distance).return_three_matrix_mut(&rotation_matrix));
-                    //to_return.push(Vector::new(x as f64, y as f64, self.distance));
-                }
-            }
-            to_return
+        //draw produces a 2 * width by 2 * height image
+        pub fn pixel_width(&self) -> u32 {
+            (2 * self.width) as u32
+        }
+        pub fn pixel_height(&self) -> u32 {
+            (2 * self.height) as u32
+        }
+        pub fn aspect(&self) -> f64 {
+            self.width as f64 / self.height as f64
         }
+        //ray through the point (x, y) in pixel coordinates, so the centre of pixel (column, row) is (column + 0.5, row + 0.5)
+        fn ray_through(&self, cam: &Camera, x: f64, y: f64) -> Vector {
+            cam.ray(x / self.width as f64 - 1.0, y / self.height as f64 - 1.0)
+        }
+        //pixel (column, row) that point shows up at, which may be off screen, or None if it is behind the camera
+        pub fn project(&self, cam: &Camera, point: &Vector) -> Option<(i64, i64)> {
+            let (x, y) = cam.frame_position(point)?;
+            Some((((x + 1.0) * self.width as f64).floor() as i64, ((y + 1.0) * self.height as f64).floor() as i64))
+        }
+    }
+    #[derive(Debug, Clone, Copy)]
+    pub enum LightKind {
+        Point, //shines from location in every direction, falling off with the square of the distance
+        Directional { direction: Vector }, //infinitely far away like the sun, so location is ignored and nothing falls off
+        Spot { direction: Vector, cone_angle: f64 }, //a point light that only shines within cone_angle degrees of direction
+        //area lights are sampled at that many random points, each a point light with a share of the intensity,
+        //so partly hidden lights cast soft shadows; more samples give smoother penumbrae
+        Rectangle { u: Vector, v: Vector, samples: u32 }, //centred on location with sides u and v
+        Sphere { radius: f64, samples: u32 } //centred on location
     }
     pub struct LightSource {
         pub location: Vector,
         pub colour: Colour,
-        pub intensity: u8
+        pub intensity: f64, //scales colour; for point and spot lights this is the brightness one unit away
+        pub kind: LightKind
+    }
+    impl LightSource {
+        pub fn point(location: Vector, colour: Colour, intensity: f64) -> LightSource {
+            LightSource { location, colour, intensity, kind: LightKind::Point }
+        }
+        pub fn directional(direction: Vector, colour: Colour, intensity: f64) -> LightSource {
+            LightSource { location: Vector::origin(), colour, intensity, kind: LightKind::Directional { direction } }
+        }
+        pub fn spot(location: Vector, direction: Vector, cone_angle: f64, colour: Colour, intensity: f64) -> LightSource {
+            LightSource { location, colour, intensity, kind: LightKind::Spot { direction, cone_angle } }
+        }
+        pub fn rectangle(centre: Vector, u: Vector, v: Vector, samples: u32, colour: Colour, intensity: f64) -> LightSource {
+            LightSource { location: centre, colour, intensity, kind: LightKind::Rectangle { u, v, samples } }
+        }
+        pub fn sphere(centre: Vector, radius: f64, samples: u32, colour: Colour, intensity: f64) -> LightSource {
+            LightSource { location: centre, colour, intensity, kind: LightKind::Sphere { radius, samples } }
+        }
+        //for each point sampled on the light: the unit vector from point towards it, how far away it is and the light
+        //arriving from it; empty when point is outside a spot light's cone
+        fn illumination(&self, point: &Vector, rng: &mut Rng) -> Vec<(Vector, f64, Rgb)> {
+            let radiance = Rgb::from_colour(&self.colour).scale(self.intensity);
+            let from_point = |location: &Vector, radiance: Rgb| {
+                let to_light = Vector::vector_between(point, location);
+                let distance = to_light.magnitude();
+                (to_light.return_multiply(1.0 / distance), distance, radiance.scale(1.0 / distance.powi(2)))
+            };
+            match self.kind {
+                LightKind::Point => vec![from_point(&self.location, radiance)],
+                LightKind::Directional { direction } => vec![(direction.return_normalised().return_multiply(-1.0), f64::INFINITY, radiance)],
+                LightKind::Spot { direction, cone_angle } => {
+                    let sample = from_point(&self.location, radiance);
+                    if -Vector::dot(&sample.0, &direction.return_normalised()) < cone_angle.to_radians().cos() {
+                        return vec![];
+                    }
+                    vec![sample]
+                }
+                LightKind::Rectangle { u, v, samples } => {
+                    let share = radiance.scale(1.0 / samples.max(1) as f64);
+                    (0..samples.max(1)).map(|_| {
+                        let offset = u.return_multiply(rng.range(-0.5, 0.5)).return_plus(&v.return_multiply(rng.range(-0.5, 0.5)));
+                        from_point(&self.location.return_plus(&offset), share)
+                    }).collect()
+                }
+                LightKind::Sphere { radius, samples } => {
+                    let share = radiance.scale(1.0 / samples.max(1) as f64);
+                    let towards_point = Vector::vector_between(&self.location, point);
+                    (0..samples.max(1)).map(|_| {
+                        //only the half of the sphere facing the point can light it
+                        let mut offset = rng.unit_vector();
+                        if Vector::dot(&offset, &towards_point) < 0.0 {
+                            offset = offset.return_multiply(-1.0);
+                        }
+                        from_point(&self.location.return_plus(&offset.return_multiply(radius)), share)
+                    }).collect()
+                }
+            }
+        }
     }
     pub struct Contents<'a> {
         pub objects: Vec<Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>>,
-        pub light: Vec<&'a LightSource>
+        pub light: Vec<&'a LightSource>,
+        pub ambient: Rgb //light reaching every surface from every direction, so faces turned from the lights are not black
+    }
+    //every object locked once for the whole frame, so render threads can share the scene without contending on the mutexes
+    struct Snapshot<'a> {
+        objects: Vec<&'a (dyn SceneObject + Send + Sync)>,
+        light: &'a [&'a LightSource],
+        ambient: Rgb,
+        bvh: Bvh
+    }
+    enum BvhNode {
+        Leaf { bounds: BoundingBox, objects: Vec<usize> },
+        Branch { bounds: BoundingBox, left: usize, right: usize }
+    }
+    //bounding volume hierarchy over the snapshot's objects, rebuilt every frame since agents move between frames;
+    //objects without a bounding box are kept aside and tested against every ray
+    struct Bvh {
+        nodes: Vec<BvhNode>,
+        unbounded: Vec<usize>
+    }
+    impl Bvh {
+        const LEAF_SIZE: usize = 4;
+
+        fn build(objects: &[&(dyn SceneObject + Send + Sync)]) -> Bvh {
+            let mut bounded = vec![];
+            let mut unbounded = vec![];
+            for (index, object) in objects.iter().enumerate() {
+                match object.bounding_box() {
+                    Some(bounds) => bounded.push((index, bounds)),
+                    None => unbounded.push(index)
+                }
+            }
+            let mut bvh = Bvh { nodes: vec![], unbounded };
+            if !bounded.is_empty() {
+                bvh.build_node(&mut bounded);
+            }
+            bvh
+        }
+        //splits the objects in half along the axis their centres are most spread out on
+        fn build_node(&mut self, items: &mut [(usize, BoundingBox)]) -> usize {
+            let bounds = items[1..].iter().fold(items[0].1, |bounds, (_, other)| bounds.merge(other));
+            let index = self.nodes.len();
+            if items.len() <= Bvh::LEAF_SIZE {
+                self.nodes.push(BvhNode::Leaf { bounds, objects: items.iter().map(|(object, _)| *object).collect() });
+                return index;
+            }
+            let centres: Vec<Vector> = items.iter().map(|(_, bounds)| bounds.centre()).collect();
+            let spread = BoundingBox::containing(&centres);
+            let size = Vector::vector_between(&spread.min, &spread.max);
+            let key = |bounds: &BoundingBox| {
+                let centre = bounds.centre();
+                if size.x >= size.y && size.x >= size.z { centre.x } else if size.y >= size.z { centre.y } else { centre.z }
+            };
+            items.sort_by(|a, b| key(&a.1).partial_cmp(&key(&b.1)).unwrap());
+            self.nodes.push(BvhNode::Leaf { bounds, objects: vec![] });
+            let (lower, upper) = items.split_at_mut(items.len() / 2);
+            let left = self.build_node(lower);
+            let right = self.build_node(upper);
+            self.nodes[index] = BvhNode::Branch { bounds, left, right };
+            index
+        }
+    }
+    //every lock is held at once, so an object listed twice would deadlock; that is caught here instead
+    fn lock_objects<'a>(content: &'a Contents) -> Vec<MutexGuard<'a, Box<dyn SceneObject + Send + Sync>>> {
+        let mut seen = HashSet::new();
+        for object in &content.objects {
+            assert!(seen.insert(Arc::as_ptr(object)), "the same object is in the contents more than once");
+        }
+        content.objects.iter().map(|object| object.lock().unwrap()).collect()
+    }
+    fn snapshot<'a>(content: &'a Contents, locked: &'a [MutexGuard<'a, Box<dyn SceneObject + Send + Sync>>]) -> Snapshot<'a> {
+        let objects: Vec<&(dyn SceneObject + Send + Sync)> = locked.iter().map(|object| &***object).collect();
+        let bvh = Bvh::build(&objects);
+        Snapshot { objects, light: &content.light, ambient: content.ambient, bvh }
+    }
+    //replaces intersect with object's hit when that is nearer
+    fn consider(object: &(dyn SceneObject + Send + Sync), ray: &Vector, starting_point: &Vector, intersect: &mut Option<IntersectionData>) {
+        //the first of intersections keeps its outward normal, which tells refraction whether the ray is leaving
+        let hit = if object.has_inside() { object.intersections(ray, starting_point).into_iter().next() } else { object.intersection(ray, starting_point) };
+        if let Some(mut inter) = hit {
+            if intersect.as_ref().is_none_or(|nearest| inter.distance < nearest.distance) {
+                let material = inter.material.unwrap_or_else(|| object.material());
+                inter.solid = object.has_inside();
+                *intersect = Some(inter.with_material(material));
+            }
+        }
     }
+    //a single ray is cheaper to test against every object than to build a bvh for, and locking the objects
+    //one at a time keeps this working when the same object is in content more than once
     pub fn nearest_intersection_data(content: &Contents, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
         let mut intersect = None;
         for object in &content.objects {
-            match object.lock().unwrap().intersection(&ray, &starting_point) {
-                None => {}
-                Some(inter) => {
-                    if let None = &intersect {
-                        intersect = Some(inter);
-                    }
-                    else {
-                        if inter.distance < intersect.as_ref().unwrap().distance {
-                            intersect = Some(inter);
-                        }
+            consider(object.lock().unwrap().as_ref(), ray, starting_point, &mut intersect);
+        }
+        intersect
+    }
+    fn nearest_in_snapshot(snapshot: &Snapshot, ray: &Vector, starting_point: &Vector) -> Option<IntersectionData> {
+        let mut intersect: Option<IntersectionData> = None;
+        for &index in &snapshot.bvh.unbounded {
+            consider(snapshot.objects[index], ray, starting_point, &mut intersect);
+        }
+        let ray_length = ray.magnitude();
+        let mut to_visit = if snapshot.bvh.nodes.is_empty() { vec![] } else { vec![0] };
+        while let Some(node) = to_visit.pop() {
+            let bounds = match &snapshot.bvh.nodes[node] {
+                BvhNode::Leaf { bounds, .. } => bounds,
+                BvhNode::Branch { bounds, .. } => bounds
+            };
+            //nothing in a box the ray reaches only after the nearest hit so far can be nearer
+            match bounds.entry(ray, starting_point) {
+                None => continue,
+                Some(t) => if intersect.as_ref().is_some_and(|nearest| t * ray_length > nearest.distance) {
+                    continue;
+                }
+            }
+            match &snapshot.bvh.nodes[node] {
+                BvhNode::Leaf { objects, .. } => {
+                    for &index in objects {
+                        consider(snapshot.objects[index], ray, starting_point, &mut intersect);
                     }
                 }
+                BvhNode::Branch { left, right, .. } => {
+                    to_visit.push(*right);
+                    to_visit.push(*left);
+                }
             }
         }
         intersect
     }
+    //whether nothing sits between the point (just off the surface along normal) and a light distance away along to_light
+    fn unobstructed(snapshot: &Snapshot, interdata: &IntersectionData, to_light: &Vector, distance: f64) -> bool {
+        match nearest_in_snapshot(snapshot, to_light, &interdata.location().return_plus(&interdata.normal)) {
+            None => true,
+            Some(data) => data.distance > distance
+        }
+    }
+    //phong shading of the point the ray hit, using the material of the object it belongs to
+    fn shade(snapshot: &Snapshot, ray: &Vector, interdata: &IntersectionData) -> Rgb {
+        let material = interdata.material();
+        let albedo = Rgb::from_colour(&material.colour);
+        let mut colour = snapshot.ambient.tint(&albedo);
+        //seeded by the point being shaded so area light samples do not depend on which thread renders it
+        let location = interdata.location;
+        let mut rng = Rng::new(location.x.to_bits() ^ location.y.to_bits().rotate_left(21) ^ location.z.to_bits().rotate_left(42));
+        for (to_light, distance, radiance) in snapshot.light.iter().flat_map(|light| light.illumination(&location, &mut rng)) {
+            if !unobstructed(snapshot, interdata, &to_light, distance) {
+                continue;
+            }
+            colour = colour.add(&radiance.tint(&albedo).scale(Vector::dot(&to_light, &interdata.normal).max(0.0) * material.diffuse));
+            let reflected = Vector::return_reflected(&to_light, &interdata.normal);
+            colour = colour.add(&radiance.scale(Vector::dot(&reflected, &ray.return_normalised()).max(0.0).powi(material.shininess) * material.specular));
+        }
+        colour
+    }
+    //direction the ray bends into when crossing from index n1 to n2 (Snell's law), None on total internal reflection
+    fn refract(ray: &Vector, normal: &Vector, n1: f64, n2: f64) -> Option<Vector> {
+        let ray = ray.return_normalised();
+        let cos_incident = -Vector::dot(&ray, normal);
+        let ratio = n1 / n2;
+        let k = 1.0 - ratio.powi(2) * (1.0 - cos_incident.powi(2));
+        if k < 0.0 {
+            return None;
+        }
+        Some(ray.return_multiply(ratio).return_plus(&normal.return_multiply(ratio * cos_incident - k.sqrt())))
+    }
+    //Schlick's approximation of the share of light reflected rather than refracted
+    fn schlick(ray: &Vector, normal: &Vector, n1: f64, n2: f64) -> f64 {
+        let mut cosine = -Vector::dot(&ray.return_normalised(), normal);
+        if n1 > n2 {
+            let sin_squared = (n1 / n2).powi(2) * (1.0 - cosine.powi(2));
+            if sin_squared > 1.0 {
+                return 1.0;
+            }
+            cosine = (1.0 - sin_squared).sqrt();
+        }
+        let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+    //colour seen along the ray, following mirror reflections and refractions until depth runs out
+    fn trace(snapshot: &Snapshot, ray: &Vector, starting_point: &Vector, depth: u32) -> Rgb {
+        let mut interdata = match nearest_in_snapshot(snapshot, ray, starting_point) {
+            None => return Rgb::from_colour(&Colour::new(30, 30, 30)),
+            Some(interdata) => interdata
+        };
+        //solids give normals pointing out of them, so a ray meeting one head on is leaving the object;
+        //open surfaces such as planes, discs and lone triangles already face the ray and have no inside
+        let leaving = interdata.solid && Vector::dot(ray, &interdata.normal) > 0.0;
+        if Vector::dot(ray, &interdata.normal) > 0.0 {
+            interdata.normal = interdata.normal.return_multiply(-1.0);
+        }
+        let normal = interdata.normal;
+        let mut colour = shade(snapshot, ray, &interdata);
+        let material = interdata.material();
+        if depth == 0 || (material.reflectivity <= 0.0 && material.transparency <= 0.0) {
+            return colour;
+        }
+        let (n1, n2) = if leaving { (material.refractive_index, 1.0) } else { (1.0, material.refractive_index) };
+        let reflected_ray = Vector::return_reflected(ray, &normal);
+        let mirror = trace(snapshot, &reflected_ray, &interdata.location().return_plus(&normal), depth - 1);
+        if material.reflectivity > 0.0 {
+            colour = colour.scale(1.0 - material.reflectivity).add(&mirror.scale(material.reflectivity));
+        }
+        if material.transparency > 0.0 {
+            let fresnel = schlick(ray, &normal, n1, n2);
+            let mut passed = mirror.scale(fresnel);
+            //open surfaces are thin sheets, so light passing through comes out of the far side unbent
+            let refracted_ray = if interdata.solid { refract(ray, &normal, n1, n2) } else { Some(*ray) };
+            if let Some(refracted_ray) = refracted_ray {
+                let refracted = trace(snapshot, &refracted_ray, &interdata.location().return_plus(&normal.return_multiply(-1.0)), depth - 1);
+                passed = passed.add(&refracted.scale(1.0 - fresnel));
+            }
+            colour = colour.scale(1.0 - material.transparency).add(&passed.scale(material.transparency));
+        }
+        colour
+    }
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Sampling {
+        Grid, //samples evenly spaced across the pixel
+        Jittered //one sample placed at random in each cell of the grid
+    }
+    pub struct RenderSettings {
+        pub max_depth: u32, //how many bounces reflected rays may take
+        pub threads: usize, //0 uses every core
+        pub tile_size: usize,
+        pub samples: u32, //each pixel is split into a samples by samples grid, one ray per cell
+        pub sampling: Sampling,
+        pub exposure: f64, //in stops, each one doubling the light reaching the image
+        pub tone_mapping: ToneMapping
+    }
+    impl Default for RenderSettings {
+        fn default() -> RenderSettings {
+            RenderSettings { max_depth: 3, threads: 0, tile_size: 16, samples: 1, sampling: Sampling::Grid, exposure: 0.0, tone_mapping: ToneMapping::Clamp }
+        }
+    }
+    //averages the samples in linear light, then exposes, tone maps and gamma encodes the result
+    fn render_pixel(snapshot: &Snapshot, cam: &Camera, screen: &Screen, settings: &RenderSettings, column: usize, row: usize) -> Vec<u8> {
+        let samples = settings.samples.max(1);
+        //seeded by pixel so jittered frames come out the same whichever thread renders them
+        let mut rng = Rng::new((row * screen.pixel_width() as usize + column) as u64);
+        let mut total = Rgb::black();
+        for i in 0..samples {
+            for j in 0..samples {
+                let (dx, dy) = match settings.sampling {
+                    Sampling::Grid => (0.5, 0.5),
+                    Sampling::Jittered => (rng.next_f64(), rng.next_f64())
+                };
+                let x = column as f64 + (i as f64 + dx) / samples as f64;
+                let y = row as f64 + (j as f64 + dy) / samples as f64;
+                total = total.add(&trace(snapshot, &screen.ray_through(cam, x, y), &cam.position, settings.max_depth));
+            }
+        }
+        let average = total.scale(1.0 / (samples * samples) as f64);
+        settings.tone_mapping.apply(&average.scale(settings.exposure.exp2())).to_srgb().get()
+    }
     pub fn draw(cam: &Camera, screen: &Screen, content: &Contents) -> Vec<u8> {
-        //TODO: add different surfaces for different objects
-        let mut pixel_data = Vec::new();
-        let screen_points = screen.points_from_camera(cam);
-        for point in screen_points {
-            let intersect = nearest_intersection_data(&content, &point, &cam.location);
-            match intersect {
-                None => {pixel_data.append(&mut vec![30, 30, 30]);}
-                Some(interdata) => {
-                    let mut diffuse: Colour = Colour::new(0, 0, 0);
-                    for light in &content.light {
-                        let distance_from_light = Vector::vector_between(&interdata.location, &light.location).magnitude();
-                        let to_light = Vector::vector_between(&interdata.location, &light.location).return_normalised();
-                        let nearest_intersection = nearest_intersection_data(&content, &to_light, &interdata.location().return_plus(&interdata.normal));
-                        match nearest_intersection {
-                            None => {
-                                let mut to_add = Colour::new(light.colour.r, light.colour.g, light.colour.b);
-                                to_add.multiply(Vector::dot(&to_light, &interdata.normal) * 2.0);
-                                diffuse.add(&to_add);
-                            }
-                            Some(data) => {
-                                if data.distance > distance_from_light {
-                                    let mut to_add = Colour::new(light.colour.r, light.colour.g, light.colour.b);
-                                    to_add.multiply(Vector::dot(&to_light, &interdata.normal) * 2.0);
-                                    diffuse.add(&to_add);
-                                }
-                            }
-                        }
+        draw_with_settings(cam, screen, content, &RenderSettings::default())
+    }
+    //the frame is cut into square tiles that worker threads take in turn until none are left;
+    //every object is locked for the whole frame, so each may only be in content once or this panics
+    pub fn draw_with_settings(cam: &Camera, screen: &Screen, content: &Contents, settings: &RenderSettings) -> Vec<u8> {
+        let (width, height) = (screen.pixel_width() as usize, screen.pixel_height() as usize);
+        let locked = lock_objects(content);
+        let snapshot = snapshot(content, &locked);
+        let tile_size = settings.tile_size.max(1);
+        let (tiles_across, tiles_down) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+        let threads = if settings.threads == 0 { thread::available_parallelism().map_or(1, |n| n.get()) } else { settings.threads };
+        let next_tile = AtomicUsize::new(0);
+        let mut pixel_data = vec![0; 3 * width * height];
+        let rendered: Vec<Vec<(usize, Vec<u8>)>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(tiles_across * tiles_down).max(1)).map(|_| scope.spawn(|| {
+                let mut done = vec![];
+                loop {
+                    let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if tile >= tiles_across * tiles_down {
+                        break;
                     }
-                    let mut specular: Colour = Colour::new(0, 0, 0);
-                    for light in &content.light {
-                        let mut to_add = Colour::new(light.colour.r, light.colour.g, light.colour.b);
-                        let distance_from_light = Vector::vector_between(&interdata.location, &light.location).magnitude();
-                        let to_light = Vector::vector_between(&interdata.location, &light.location).return_normalised();
-                        let nearest_intersection = nearest_intersection_data(&content, &to_light, &interdata.location().return_plus(&interdata.normal));
-                        match nearest_intersection {
-                            None => {
-                                let reflected = Vector::return_reflected(&to_light, &interdata.normal);
-                                to_add.multiply(Vector::dot(&reflected, &interdata.location.return_normalised()).powi(4));
-                                specular.add(&to_add);
-                            }
-                            Some(data) => {
-                                if data.distance > distance_from_light {
-                                    let reflected = Vector::return_reflected(&to_light, &interdata.normal);
-                                    to_add.multiply(Vector::dot(&reflected, &interdata.location.return_normalised()).powi(4));
-                                    specular.add(&to_add);
-                                }
-                            }
+                    let (left, top) = ((tile % tiles_across) * tile_size, (tile / tiles_across) * tile_size);
+                    let mut tile_data = vec![];
+                    for y in top..(top + tile_size).min(height) {
+                        for x in left..(left + tile_size).min(width) {
+                            tile_data.append(&mut render_pixel(&snapshot, cam, screen, settings, x, y));
                         }
                     }
-                    diffuse.add(&specular);
-                    pixel_data.append(&mut diffuse.get());
+                    done.push((tile, tile_data));
                 }
+                done
+            })).collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+        for (tile, tile_data) in rendered.into_iter().flatten() {
+            let (left, top) = ((tile % tiles_across) * tile_size, (tile / tiles_across) * tile_size);
+            let tile_width = (left + tile_size).min(width) - left;
+            for (row, row_data) in tile_data.chunks(3 * tile_width).enumerate() {
+                let start = 3 * ((top + row) * width + left);
+                pixel_data[start..start + row_data.len()].copy_from_slice(row_data);
             }
         }
         pixel_data
@@ -217,3 +1433,181 @@ impl Colour {
         self.g = (self.g as f64 * m.abs()) as u8;
     }
 }
+//linear light, unbounded so bright lights and highlights are not clipped until the frame is tone mapped
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rgb {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64
+}
+impl Rgb {
+    pub fn new(r: f64, g: f64, b: f64) -> Rgb {
+        Rgb { r, g, b }
+    }
+    pub fn black() -> Rgb {
+        Rgb::new(0.0, 0.0, 0.0)
+    }
+    //colours are written in sRGB, so they are decoded to linear light before any maths is done on them
+    pub fn from_colour(colour: &Colour) -> Rgb {
+        fn decode(channel: u8) -> f64 {
+            let c = channel as f64 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        }
+        Rgb::new(decode(colour.r), decode(colour.g), decode(colour.b))
+    }
+    //channels outside 0 to 1 are clamped, so tone map first to keep detail in bright areas
+    pub fn to_srgb(&self) -> Colour {
+        fn encode(c: f64) -> u8 {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round() as u8
+        }
+        Colour::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+    pub fn add(&self, other: &Rgb) -> Rgb {
+        Rgb::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+    pub fn scale(&self, factor: f64) -> Rgb {
+        Rgb::new(self.r * factor, self.g * factor, self.b * factor)
+    }
+    //filters this light through another colour, e.g. light falling on a coloured surface
+    pub fn tint(&self, other: &Rgb) -> Rgb {
+        Rgb::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+//maps unbounded linear light into the 0 to 1 range an image can show
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMapping {
+    Clamp, //anything brighter than white is cut off
+    Reinhard, //x / (1 + x), never quite reaching white
+    Aces //Narkowicz's fit of the ACES filmic curve
+}
+impl ToneMapping {
+    pub fn apply(&self, colour: &Rgb) -> Rgb {
+        let map = |c: f64| match self {
+            ToneMapping::Clamp => c.clamp(0.0, 1.0),
+            ToneMapping::Reinhard => c.max(0.0) / (1.0 + c.max(0.0)),
+            ToneMapping::Aces => {
+                let c = c.max(0.0);
+                (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }
+        };
+        Rgb::new(map(colour.r), map(colour.g), map(colour.b))
+    }
+}
+//how a surface responds to light: colour is the albedo that tints diffuse light, specular highlights keep the light's colour
+#[derive(Copy, Clone, Debug)]
+pub struct Material {
+    pub colour: Colour,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: i32,
+    pub reflectivity: f64, //0 is matte, 1 a perfect mirror
+    pub transparency: f64, //share of light that passes into the object instead of being shaded on its surface
+    pub refractive_index: f64
+}
+impl Material {
+    pub fn new(colour: Colour) -> Material {
+        Material { colour, ..Material::default() }
+    }
+}
+impl Default for Material {
+    //plain white, matching how every object was shaded before materials existed
+    fn default() -> Material {
+        Material { colour: Colour::new(255, 255, 255), diffuse: 2.0, specular: 1.0, shininess: 4, reflectivity: 0.0, transparency: 0.0, refractive_index: 1.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::Vector;
+    use std::sync::{Arc, Mutex};
+    use crate::raytracer::{Material, Rgb, SceneObject};
+    use crate::raytracer::scene::{Camera, Contents, Screen, draw, nearest_intersection_data};
+    use crate::raytracer::scene_objects::{Csg, Mesh, Sphere, Triangle};
+
+    fn cube_triangles() -> Vec<Triangle> {
+        let corner = |x: f64, y: f64, z: f64| Vector::new(x, y, z);
+        let quads = [
+            [corner(0.0, 0.0, 0.0), corner(0.0, 1.0, 0.0), corner(1.0, 1.0, 0.0), corner(1.0, 0.0, 0.0)],
+            [corner(0.0, 0.0, 1.0), corner(1.0, 0.0, 1.0), corner(1.0, 1.0, 1.0), corner(0.0, 1.0, 1.0)],
+            [corner(0.0, 0.0, 0.0), corner(1.0, 0.0, 0.0), corner(1.0, 0.0, 1.0), corner(0.0, 0.0, 1.0)],
+            [corner(0.0, 1.0, 0.0), corner(0.0, 1.0, 1.0), corner(1.0, 1.0, 1.0), corner(1.0, 1.0, 0.0)],
+            [corner(0.0, 0.0, 0.0), corner(0.0, 0.0, 1.0), corner(0.0, 1.0, 1.0), corner(0.0, 1.0, 0.0)],
+            [corner(1.0, 0.0, 0.0), corner(1.0, 1.0, 0.0), corner(1.0, 1.0, 1.0), corner(1.0, 0.0, 1.0)],
+        ];
+        quads.iter().flat_map(|[a, b, c, d]| [Triangle::new(*a, *b, *c), Triangle::new(*a, *c, *d)]).collect()
+    }
+
+    #[test]
+    fn only_closed_consistently_wound_meshes_have_an_inside() {
+        assert!(Mesh::new(cube_triangles()).is_closed());
+        let mut open = cube_triangles();
+        open.truncate(10);
+        assert!(!Mesh::new(open).is_closed());
+        let mut flipped = cube_triangles();
+        let Triangle { a, b, c, .. } = flipped[0].clone();
+        flipped[0] = Triangle::new(a, c, b);
+        assert!(!Mesh::new(flipped).is_closed());
+        assert!(!Mesh::new(vec![]).is_closed());
+    }
+
+    //spheres of radius 2 centred on x = -1 and x = 1, so along the x axis left spans -3 to 1 and right -1 to 3
+    fn overlapping_spheres() -> (Box<dyn SceneObject + Send + Sync>, Box<dyn SceneObject + Send + Sync>) {
+        let sphere = |x: f64| Box::new(Sphere { radius: 2.0, location: Vector::new(x, 0.0, 0.0), material: Material::default() });
+        (sphere(-1.0), sphere(1.0))
+    }
+    //distance and normal of every hit of a ray along +x starting at x, rounded so exact values can be compared
+    fn hits_along_x(csg: &Csg, x: f64) -> Vec<(f64, [f64; 3])> {
+        let round = |value: f64| (value * 1e6).round() / 1e6;
+        csg.intersections(&Vector::new(1.0, 0.0, 0.0), &Vector::new(x, 0.0, 0.0)).iter().map(|hit| {
+            let normal = hit.normal();
+            (round(hit.distance()), [round(normal.x), round(normal.y), round(normal.z)])
+        }).collect()
+    }
+
+    #[test]
+    fn csg_union_keeps_the_outer_surfaces() {
+        let (left, right) = overlapping_spheres();
+        let union = Csg::union(left, right);
+        assert_eq!(hits_along_x(&union, -10.0), vec![(7.0, [-1.0, 0.0, 0.0]), (13.0, [1.0, 0.0, 0.0])]);
+        assert_eq!(hits_along_x(&union, -2.0), vec![(5.0, [1.0, 0.0, 0.0])]);
+    }
+
+    #[test]
+    fn csg_intersection_keeps_the_overlap() {
+        let (left, right) = overlapping_spheres();
+        let intersection = Csg::intersection(left, right);
+        assert_eq!(hits_along_x(&intersection, -10.0), vec![(9.0, [-1.0, 0.0, 0.0]), (11.0, [1.0, 0.0, 0.0])]);
+        assert_eq!(hits_along_x(&intersection, -2.0), vec![(1.0, [-1.0, 0.0, 0.0]), (3.0, [1.0, 0.0, 0.0])]);
+    }
+
+    #[test]
+    fn csg_difference_flips_normals_on_the_cut() {
+        let (left, right) = overlapping_spheres();
+        let difference = Csg::difference(left, right);
+        //leaving the difference through the right sphere's surface, whose normal has to point out of the difference
+        assert_eq!(hits_along_x(&difference, -10.0), vec![(7.0, [-1.0, 0.0, 0.0]), (9.0, [1.0, 0.0, 0.0])]);
+        assert_eq!(hits_along_x(&difference, -2.0), vec![(1.0, [1.0, 0.0, 0.0])]);
+        assert_eq!(hits_along_x(&difference, 0.0), vec![]);
+    }
+
+    fn contents_with_one_sphere_twice() -> Contents<'static> {
+        let sphere: Arc<Mutex<Box<dyn SceneObject + Send + Sync>>> = Arc::new(Mutex::new(Box::new(Sphere { radius: 1.0, location: Vector::new(0.0, 0.0, 5.0), material: Material::default() })));
+        Contents { objects: vec![sphere.clone(), sphere], light: vec![], ambient: Rgb::black() }
+    }
+
+    #[test]
+    fn single_rays_handle_objects_listed_twice() {
+        let hit = nearest_intersection_data(&contents_with_one_sphere_twice(), &Vector::new(0.0, 0.0, 1.0), &Vector::origin()).unwrap();
+        assert!((hit.distance() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than once")]
+    fn drawing_rejects_objects_listed_twice() {
+        let screen = Screen { height: 1, width: 1 };
+        let camera = Camera::look_at(Vector::origin(), Vector::new(0.0, 0.0, 1.0), Vector::new(0.0, -1.0, 0.0), 45.0, screen.aspect());
+        draw(&camera, &screen, &contents_with_one_sphere_twice());
+    }
+}
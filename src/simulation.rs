@@ -0,0 +1,236 @@
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::agents::{Agent, BasicAgent};
+use crate::behaviours::Behaviour;
+use crate::image;
+use crate::network::{ChannelMesh, MeshBuilder, Topology};
+use crate::matrices::Vector;
+use crate::raytracer::{Colour, Rgb, SceneObject};
+use crate::raytracer::scene::{Camera, Contents, draw_with_settings, LightSource, RenderSettings, Screen};
+
+//everything that is drawn besides the agents themselves
+pub struct Scene {
+    pub camera: Camera,
+    pub screen: Screen,
+    pub lights: Vec<LightSource>,
+    pub ambient: Rgb,
+    pub objects: Vec<Arc<Mutex<Box<dyn SceneObject + Send + Sync>>>>,
+    pub settings: RenderSettings,
+}
+//Threaded lets every agent run its own act thread and is subject to scheduling,
+//Lockstep has all agents publish tick N before any of them consumes it, so runs are reproducible
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    Threaded,
+    Lockstep,
+}
+//called with the simulation after every step
+type TickHook<B> = Box<dyn FnMut(&Simulation<B>)>;
+pub struct Simulation<B: Behaviour + 'static> {
+    agents: Vec<Arc<Mutex<BasicAgent<B>>>>,
+    mesh: ChannelMesh,
+    topology: Topology,
+    communication_radius: Option<f64>,
+    tick: u64,
+    mode: StepMode,
+    scene: Scene,
+    hooks: Vec<TickHook<B>>,
+}
+impl<B: Behaviour + 'static> Simulation<B> {
+    //every agent gets an id matching its position in agents and can talk to every other agent
+    pub fn new(scene: Scene, agents: Vec<(Box<dyn SceneObject + Send + Sync>, B)>) -> Simulation<B> {
+        let mesh = MeshBuilder::fully_connected(agents.len());
+        Simulation::with_mesh(scene, agents, mesh)
+    }
+    pub fn with_mesh(scene: Scene, agents: Vec<(Box<dyn SceneObject + Send + Sync>, B)>, mesh: MeshBuilder) -> Simulation<B> {
+        assert_eq!(agents.len(), mesh.size(), "the mesh must have one endpoint per agent");
+        let topology = mesh.topology().clone();
+        let (mesh, endpoints) = mesh.build();
+        let agents = agents.into_iter().zip(endpoints).enumerate().map(|(id, ((body, behaviour), endpoint))| {
+            Arc::new(Mutex::new(BasicAgent::new(id as i64, Arc::new(Mutex::new(body)), endpoint, behaviour)))
+        }).collect();
+        Simulation { agents, mesh, topology, communication_radius: None, tick: 0, mode: StepMode::Threaded, scene, hooks: vec![] }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn agents(&self) -> &Vec<Arc<Mutex<BasicAgent<B>>>> {
+        &self.agents
+    }
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+    //rewires every agent to talk along the links of topology from the next step on
+    pub fn set_topology(&mut self, topology: Topology) {
+        assert_eq!(topology.size(), self.agents.len(), "the topology must have one node per agent");
+        for (id, agent) in self.agents.iter().enumerate() {
+            agent.lock().unwrap().rewire(self.mesh.senders_for(&topology, id), topology.in_degree(id));
+        }
+        self.topology = topology;
+    }
+    pub fn communication_radius(&self) -> Option<f64> {
+        self.communication_radius
+    }
+    //with a radius set, agents only talk to agents whose bodies are within it, recomputed at the start of every step
+    pub fn set_communication_radius(&mut self, radius: Option<f64>) {
+        self.communication_radius = radius;
+    }
+    pub fn mode(&self) -> StepMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: StepMode) {
+        self.mode = mode;
+    }
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    //hooks run after every step, once all agents have moved
+    pub fn on_tick(&mut self, hook: impl FnMut(&Simulation<B>) + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn step(&mut self) {
+        if let Some(radius) = self.communication_radius {
+            let locations: Vec<Vector> = self.agents.iter().map(|agent| agent.lock().unwrap().get_location()).collect();
+            self.set_topology(Topology::within_radius(&locations, radius));
+        }
+        match self.mode {
+            StepMode::Threaded => {
+                let mut handles = vec![];
+                for agent in &self.agents {
+                    handles.push(agent.lock().unwrap().act(agent.clone()));
+                }
+                for handle in handles {
+                    handle.join().unwrap();
+                }
+            }
+            StepMode::Lockstep => {
+                for agent in &self.agents {
+                    agent.lock().unwrap().publish();
+                }
+                //every tick-N message is now queued, so agents can update in parallel without seeing each other's moves
+                thread::scope(|scope| {
+                    for agent in &self.agents {
+                        scope.spawn(move || agent.lock().unwrap().consume());
+                    }
+                });
+            }
+        }
+        self.tick += 1;
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook(self);
+        }
+        self.hooks = hooks;
+    }
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    pub fn contents(&self) -> Contents<'_> {
+        let mut objects = self.scene.objects.clone();
+        for agent in &self.agents {
+            objects.push(agent.lock().unwrap().get_body());
+        }
+        Contents { objects, light: self.scene.lights.iter().collect(), ambient: self.scene.ambient }
+    }
+    pub fn render(&self) -> Vec<u8> {
+        draw_with_settings(&self.scene.camera, &self.scene.screen, &self.contents(), &self.scene.settings)
+    }
+    //steps ticks times and returns the frame rendered after every step
+    pub fn record(&mut self, ticks: u64) -> Vec<Vec<u8>> {
+        let mut frames = vec![];
+        for _ in 0..ticks {
+            self.step();
+            frames.push(self.render());
+        }
+        frames
+    }
+    //renders the current frame straight to a .png or .ppm file, no display needed
+    pub fn render_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let screen = &self.scene.screen;
+        image::write_image(path, screen.pixel_width(), screen.pixel_height(), &self.render())
+    }
+    //steps ticks times, writing the frame after every step to directory/frame_00000.png and so on
+    pub fn run_to_files(&mut self, ticks: u64, directory: impl AsRef<Path>) -> io::Result<()> {
+        let frames = self.record(ticks);
+        let screen = &self.scene.screen;
+        image::write_png_sequence(directory, "frame", screen.pixel_width(), screen.pixel_height(), &frames)?;
+        Ok(())
+    }
+    //draws every link of the topology as a line between the agents it connects on top of a rendered frame
+    pub fn draw_topology(&self, pixel_data: &mut [u8], colour: &Colour) {
+        let screen = &self.scene.screen;
+        let locations: Vec<Vector> = self.agents.iter().map(|agent| agent.lock().unwrap().get_location()).collect();
+        let projected: Vec<Option<(i64, i64)>> = locations.iter().map(|location| screen.project(&self.scene.camera, location)).collect();
+        for (from, to) in self.topology.links() {
+            if let (Some(start), Some(end)) = (projected[from], projected[to]) {
+                draw_line(pixel_data, 2 * screen.width, 2 * screen.height, start, end, colour);
+            }
+        }
+    }
+}
+
+fn draw_line(pixel_data: &mut [u8], width: i64, height: i64, start: (i64, i64), end: (i64, i64), colour: &Colour) {
+    //lines to agents projected far off screen are not worth walking pixel by pixel
+    let limit = 4 * (width + height);
+    if [start.0, start.1, end.0, end.1].iter().any(|c| c.abs() > limit) {
+        return;
+    }
+    let steps = (end.0 - start.0).abs().max((end.1 - start.1).abs()).max(1);
+    for step in 0..=steps {
+        let x = start.0 + (end.0 - start.0) * step / steps;
+        let y = start.1 + (end.1 - start.1) * step / steps;
+        if x >= 0 && x < width && y >= 0 && y < height {
+            let index = 3 * (y * width + x) as usize;
+            pixel_data[index..index + 3].copy_from_slice(&colour.get());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviours::Attraction;
+    use crate::raytracer::Rgb;
+    use crate::raytracer::scene::Camera;
+    use crate::raytracer::scene_objects::Sphere;
+
+    fn lockstep_simulation() -> Simulation<Attraction> {
+        let screen = Screen { height: 10, width: 10 };
+        let camera = Camera::look_at(Vector::origin(), Vector::new(0.0, 0.0, 1.0), Vector::new(0.0, -1.0, 0.0), 45.0, screen.aspect());
+        let scene = Scene { camera, screen, lights: vec![], ambient: Rgb::black(), objects: vec![], settings: RenderSettings::default() };
+        let agents = (0..6).map(|id| {
+            let body: Box<dyn SceneObject + Send + Sync> = Box::new(Sphere { radius: 20.0, location: Vector::new(100.0 * id as f64, 40.0 * (id % 3) as f64, 1000.0 + 7.0 * id as f64), material: Default::default() });
+            (body, Attraction::default())
+        }).collect();
+        let mut simulation = Simulation::new(scene, agents);
+        simulation.set_mode(StepMode::Lockstep);
+        simulation
+    }
+    fn locations(simulation: &Simulation<Attraction>) -> Vec<(f64, f64, f64)> {
+        simulation.agents().iter().map(|agent| {
+            let location = agent.lock().unwrap().get_location();
+            (location.x, location.y, location.z)
+        }).collect()
+    }
+
+    #[test]
+    fn lockstep_runs_are_reproducible() {
+        let mut first = lockstep_simulation();
+        let mut second = lockstep_simulation();
+        first.run(50);
+        second.run(50);
+        assert_eq!(locations(&first), locations(&second));
+        assert_ne!(locations(&first), locations(&lockstep_simulation()), "the agents should have moved");
+    }
+}